@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use timer_module::clock::{Clock, MonotonicClock};
+use timer_module::metrics::TimeFormatterNs;
+use timer_module::profiler::{CallSampling, TimeProfiler};
+
+const CALLS: usize = 1_000_000;
+const THREADS: usize = 4;
+
+fn tiny(value: u64) -> u64 {
+    black_box(value).wrapping_mul(3)
+}
+
+fn report(label: &str, percall_ns: f64, baseline_ns: f64) {
+    let percall = TimeFormatterNs::new(percall_ns).auto_format();
+    let overhead = TimeFormatterNs::new(percall_ns - baseline_ns).auto_format();
+    println!(
+        "{:<24} {:>10}/call — overhead {:>10}",
+        label, percall, overhead
+    );
+}
+
+type Metrics = HashMap<u64, (usize, f64)>;
+
+// The recording path before it was made lock-free: both metric maps are
+// locked to find the primary call, and again to add the measured time.
+#[derive(Default)]
+struct LockedRecorder {
+    callable_refs: Mutex<Metrics>,
+    timing_refs: Mutex<HashMap<u64, Metrics>>,
+    clock: MonotonicClock,
+}
+
+impl LockedRecorder {
+    fn record<F>(&self, call_hash: u64, function: F, value: u64) -> u64
+    where
+        F: Fn(u64) -> u64,
+    {
+        {
+            let callable_refs = self.callable_refs.lock().unwrap();
+            let timing_refs = self.timing_refs.lock().unwrap();
+            black_box((callable_refs.get(&call_hash), timing_refs.get(&call_hash)));
+        }
+        let start_time: Duration = self.clock.now();
+        let result: u64 = function(value);
+        let time_ns: f64 = self.clock.now().saturating_sub(start_time).as_nanos() as f64;
+
+        let mut callable_refs = self.callable_refs.lock().unwrap();
+        let mut timing_refs = self.timing_refs.lock().unwrap();
+        let call_metrics = callable_refs.entry(call_hash).or_default();
+        call_metrics.0 += 1;
+        call_metrics.1 += time_ns;
+        timing_refs.entry(call_hash).or_default();
+        result
+    }
+}
+
+fn measure<F>(calls: usize, function: F) -> f64
+where
+    F: Fn(u64) -> u64,
+{
+    let start_time: Instant = Instant::now();
+    for value in 0..calls as u64 {
+        black_box(function(value));
+    }
+    start_time.elapsed().as_nanos() as f64 / calls as f64
+}
+
+fn main() {
+    let baseline_ns: f64 = measure(CALLS, tiny);
+    report("unwrapped", baseline_ns, baseline_ns);
+
+    let clock: MonotonicClock = MonotonicClock::new();
+    let clock_ns: f64 = measure(CALLS, |value| clock.now().as_nanos() as u64 ^ tiny(value));
+    report("one clock read", clock_ns, baseline_ns);
+
+    let recorder: LockedRecorder = LockedRecorder::default();
+    let locked_ns: f64 = measure(CALLS, |value| recorder.record(1, tiny, value));
+    report("locked maps (previous)", locked_ns, baseline_ns);
+
+    let profiler: TimeProfiler = TimeProfiler::new(false);
+    let wrapped = profiler.function_wrapper(tiny);
+    report("primary call", measure(CALLS, &wrapped), baseline_ns);
+
+    // Never timed after the first call, so only the bookkeeping is left.
+    let wrapped = profiler.sampled_function_wrapper(CallSampling::EveryNth(u64::MAX), tiny);
+    report(
+        "untimed primary call",
+        measure(CALLS, &wrapped),
+        baseline_ns,
+    );
+
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(TimeProfiler::new(false)));
+    let inner = profiler.function_wrapper(tiny);
+    let outer = profiler.function_wrapper(move |calls: usize| measure(calls, &inner));
+    let nested_ns: f64 = outer(CALLS);
+    report("nested call", nested_ns, baseline_ns);
+
+    let profiler: TimeProfiler = TimeProfiler::new(false);
+    let wrapped = profiler.function_wrapper(tiny);
+    let start_time: Instant = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| measure(CALLS, &wrapped));
+        }
+    });
+    let threaded_ns: f64 = start_time.elapsed().as_nanos() as f64 / (CALLS * THREADS) as f64;
+    report("primary call, 4 threads", threaded_ns, baseline_ns);
+}
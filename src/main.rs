@@ -1,9 +1,10 @@
 mod test;
-mod timer_module;
 
 use test::algorithm_test::{binary_digits, generate_binary_combinations};
 use test::algorithm_test::{binary_search_value, generate_array};
 
+use timer_module::benchmark::{Benchmark, BenchmarkSummary};
+use timer_module::budget::{BudgetReport, PerformanceBudget};
 use timer_module::profiler::TimeProfiler;
 use timer_module::timer::TimerModule;
 
@@ -11,41 +12,66 @@ use std::thread::sleep;
 use std::time::Duration;
 
 fn main() {
-    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profile_function();
+    profile_function2();
+    profile_function3();
 
-    profile_function(&mut profiler);
-    profile_function2(&mut profiler);
-    profile_function3(&mut profiler);
-
-    profiler.print_report();
+    TimeProfiler::global().print_report();
+    budget_example();
 
     timer_example();
+    if std::env::args().any(|arg| arg == "--benchmark") {
+        benchmark_example();
+    }
 }
 
-fn profile_function(profiler: &mut TimeProfiler) {
+fn profile_function() {
     let array: Vec<i32> = generate_array(10_000);
-    let mut function3 = profiler.function_wrapper(|(a, b)| binary_search_value(a, b));
+    let function3 = TimeProfiler::global()
+        .keyed_function_wrapper("binary_search_value", |(a, b)| binary_search_value(a, b));
 
     for value in array.iter() {
         function3((&array, *value));
     }
 }
 
-fn profile_function2(profiler: &mut TimeProfiler) {
-    let mut function3 = profiler.function_wrapper(binary_digits);
+fn profile_function2() {
+    let function3 = TimeProfiler::global().function_wrapper(binary_digits);
 
     function3(12);
 }
 
-fn profile_function3(profiler: &mut TimeProfiler) {
-    let mut function3 = profiler.function_wrapper(generate_binary_combinations);
+fn profile_function3() {
+    let function3 = TimeProfiler::global().function_wrapper(generate_binary_combinations);
 
     function3(12);
 }
 
+fn budget_example() {
+    let mut budget: PerformanceBudget = PerformanceBudget::new();
+    budget
+        .add_percall_limit("binary_search_value", Duration::from_micros(5))
+        .add_total_limit("generate_binary_combinations", Duration::from_millis(10));
+
+    let report: BudgetReport = TimeProfiler::global().check_budget(&budget);
+    println!("{}", report.get_string());
+}
+
 fn timer_example() {
     let mut timer_module = TimerModule::new();
     timer_module.start();
     sleep(Duration::from_millis(100));
     println!("Timer Example: {:?}", timer_module)
 }
+
+fn benchmark_example() {
+    let array: Vec<i32> = generate_array(10_000);
+    let mut benchmark: Benchmark = Benchmark::new("binary_search_value");
+    benchmark
+        .set_warm_up_time(Duration::from_millis(200))
+        .set_measurement_time(Duration::from_secs(1));
+
+    let summary: BenchmarkSummary =
+        benchmark.run_with_input(array, |array| binary_search_value(array, 5_000));
+    println!("{}", summary.get_string());
+}
@@ -38,9 +38,7 @@ fn binary_search_recursive(array: &[i32], value: i32, mut low_idx: i32, mut high
     }
 
     if m_value < value {
-        if h_value > m_value && h_value > value {
-            low_idx = mid_idx;
-        } else if h_value < m_value {
+        if (h_value > m_value && h_value > value) || h_value < m_value {
             low_idx = mid_idx;
         } else if h_value < value {
             high_idx = mid_idx;
@@ -48,21 +46,19 @@ fn binary_search_recursive(array: &[i32], value: i32, mut low_idx: i32, mut high
     } else if m_value > value {
         if h_value < m_value && l_value > value {
             low_idx = mid_idx;
-        } else if h_value > m_value {
-            high_idx = mid_idx;
-        } else if h_value < value {
+        } else if h_value > m_value || h_value < value {
             high_idx = mid_idx;
         }
     }
     let idx: i32 = binary_search_recursive(array, value, low_idx, high_idx);
 
-    return idx;
+    idx
 }
 
 pub fn binary_search_value(array: &[i32], value: i32) -> i32 {
     let low_idx: i32 = 0;
     let high_idx: i32 = (array.len() - 1) as i32;
-    let idx: i32 = binary_search_recursive(&array, value, low_idx, high_idx);
+    let idx: i32 = binary_search_recursive(array, value, low_idx, high_idx);
     idx
 }
 
@@ -1 +1,2 @@
-pub mod algorithm_test;
\ No newline at end of file
+#[allow(dead_code)]
+pub mod algorithm_test;
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::comparison::{self, BaselineComparison};
+use super::metrics::CallableMetrics;
+
+const BASELINE_HEADER: &str = "# timer_module baseline v1";
+const BASELINE_FIELDS: usize = 12;
+const SUBCALL_PREFIX: &str = ">\t";
+const PRIMARY_PREFIX: &str = "*\t";
+const PROBE_OFFSET: u64 = 0xcbf29ce484222325;
+const PROBE_PRIME: u64 = 0x100000001b3;
+
+#[derive(Debug)]
+pub enum BaselineError {
+    Io(io::Error),
+    MissingHeader,
+    InvalidLine(usize),
+}
+
+impl Display for BaselineError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            BaselineError::Io(error) => write!(f, "cannot access baseline: {}", error),
+            BaselineError::MissingHeader => write!(f, "not a baseline file"),
+            BaselineError::InvalidLine(line) => {
+                write!(f, "invalid baseline entry at line {}", line)
+            }
+        }
+    }
+}
+
+impl Error for BaselineError {}
+
+impl From<io::Error> for BaselineError {
+    fn from(error: io::Error) -> Self {
+        BaselineError::Io(error)
+    }
+}
+
+/// A saved profile: every callable's totals, plus the subcalls recorded
+/// beneath each primary call keyed by the primary's call hash. Every primary
+/// call has an entry, empty when nothing was called beneath it.
+#[derive(Clone, Debug, Default)]
+pub struct ProfileBaseline {
+    pub calls: Vec<CallableMetrics>,
+    pub subcalls: HashMap<u64, Vec<CallableMetrics>>,
+}
+
+impl ProfileBaseline {
+    pub fn from_metrics(
+        callable_metrics: &HashMap<u64, CallableMetrics>,
+        timing_metrics: &HashMap<u64, HashMap<u64, CallableMetrics>>,
+    ) -> Self {
+        let mut calls: Vec<CallableMetrics> = callable_metrics.values().cloned().collect();
+        sort_calls(&mut calls);
+        let mut subcalls: HashMap<u64, Vec<CallableMetrics>> = HashMap::new();
+        for (pcall_hash, pcall_subcalls) in timing_metrics.iter() {
+            let mut pcall_subcalls: Vec<CallableMetrics> =
+                pcall_subcalls.values().cloned().collect();
+            sort_calls(&mut pcall_subcalls);
+            subcalls.insert(*pcall_hash, pcall_subcalls);
+        }
+        ProfileBaseline { calls, subcalls }
+    }
+
+    pub fn is_primary(&self, call_hash: u64) -> bool {
+        self.subcalls.contains_key(&call_hash)
+    }
+
+    /// Sums another profile into this one, e.g. the profiles of several test
+    /// shards. Callables are matched by module and name.
+    pub fn merge(&mut self, other: &ProfileBaseline) -> &mut Self {
+        let mut call_hashes: HashMap<u64, u64> = HashMap::new();
+        for call_metrics in other.calls.iter() {
+            let merged_call = self.calls.iter_mut().find(|merged| *merged == call_metrics);
+            let call_hash: u64 = match merged_call {
+                Some(merged_call) => {
+                    merged_call.merge(call_metrics);
+                    merged_call.call_hash
+                }
+                None => {
+                    let mut call_metrics: CallableMetrics = call_metrics.clone();
+                    call_metrics.call_hash = self.probe_call_hash(call_metrics.call_hash);
+                    self.calls.push(call_metrics);
+                    self.calls[self.calls.len() - 1].call_hash
+                }
+            };
+            call_hashes.insert(call_metrics.call_hash, call_hash);
+        }
+
+        for (pcall_hash, subcalls) in other.subcalls.iter() {
+            let pcall_hash: u64 = call_hashes.get(pcall_hash).copied().unwrap_or(*pcall_hash);
+            let merged_subcalls: &mut Vec<CallableMetrics> =
+                self.subcalls.entry(pcall_hash).or_default();
+            for subcall_metrics in subcalls.iter() {
+                match merged_subcalls
+                    .iter_mut()
+                    .find(|merged| *merged == subcall_metrics)
+                {
+                    Some(merged_subcall) => merged_subcall.merge(subcall_metrics),
+                    None => {
+                        let mut subcall_metrics: CallableMetrics = subcall_metrics.clone();
+                        let call_hash: Option<&u64> = call_hashes.get(&subcall_metrics.call_hash);
+                        subcall_metrics.call_hash =
+                            call_hash.copied().unwrap_or(subcall_metrics.call_hash);
+                        merged_subcalls.push(subcall_metrics);
+                    }
+                }
+            }
+        }
+        sort_calls(&mut self.calls);
+        self
+    }
+
+    // A callable new to this profile keeps its call hash unless another
+    // callable here already has it, in which case the hash is re-hashed
+    // until it is free.
+    fn probe_call_hash(&self, call_hash: u64) -> u64 {
+        let mut probed_hash: u64 = call_hash;
+        while self.calls.iter().any(|call| call.call_hash == probed_hash) {
+            probed_hash = (probed_hash ^ PROBE_OFFSET).wrapping_mul(PROBE_PRIME);
+        }
+        probed_hash
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> std::result::Result<Self, BaselineError> {
+        let text: String = fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.get_string())
+    }
+
+    pub fn parse(text: &str) -> std::result::Result<Self, BaselineError> {
+        let mut lines = text.lines();
+        if lines.next() != Some(BASELINE_HEADER) {
+            return Err(BaselineError::MissingHeader);
+        }
+        let mut profile: ProfileBaseline = ProfileBaseline::default();
+        for (index, line) in lines.enumerate().filter(|(_, line)| !line.is_empty()) {
+            let invalid_line = BaselineError::InvalidLine(index + 2);
+            let (pcall_hash, line) = match line.strip_prefix(SUBCALL_PREFIX) {
+                Some(line) => line.split_once('\t').ok_or(invalid_line)?,
+                None => {
+                    let primary_line: Option<&str> = line.strip_prefix(PRIMARY_PREFIX);
+                    let call_line: &str = primary_line.unwrap_or(line);
+                    let call_metrics = parse_call_metrics(call_line).ok_or(invalid_line)?;
+                    if primary_line.is_some() {
+                        profile.subcalls.entry(call_metrics.call_hash).or_default();
+                    }
+                    profile.calls.push(call_metrics);
+                    continue;
+                }
+            };
+            let pcall_hash: u64 = pcall_hash
+                .parse()
+                .map_err(|_| BaselineError::InvalidLine(index + 2))?;
+            let subcall_metrics =
+                parse_call_metrics(line).ok_or(BaselineError::InvalidLine(index + 2))?;
+            profile
+                .subcalls
+                .entry(pcall_hash)
+                .or_default()
+                .push(subcall_metrics);
+        }
+        Ok(profile)
+    }
+
+    pub fn get_string(&self) -> String {
+        let mut string: String = format!("{}\n", BASELINE_HEADER);
+        for call_metrics in self.calls.iter() {
+            if self.is_primary(call_metrics.call_hash) {
+                string += PRIMARY_PREFIX;
+            }
+            string += &format_call_metrics(call_metrics);
+            for subcall_metrics in self
+                .subcalls
+                .get(&call_metrics.call_hash)
+                .into_iter()
+                .flatten()
+            {
+                string += &format!("{}{}\t", SUBCALL_PREFIX, call_metrics.call_hash);
+                string += &format_call_metrics(subcall_metrics);
+            }
+        }
+        string
+    }
+
+    /// Matches callables by call hash, which is derived from their stable
+    /// identity, and falls back to module and name where that is unique.
+    pub fn compare(
+        &self,
+        callable_metrics: &HashMap<u64, CallableMetrics>,
+        noise_threshold_prc: f64,
+    ) -> BaselineComparison {
+        comparison::compare_calls(&self.calls, callable_metrics, noise_threshold_prc)
+    }
+}
+
+fn sort_calls(calls: &mut [CallableMetrics]) {
+    calls.sort_by(|a, b| (&a.module, &a.name).cmp(&(&b.module, &b.name)));
+}
+
+fn format_call_metrics(call_metrics: &CallableMetrics) -> String {
+    let fields: [String; BASELINE_FIELDS] = [
+        escape_field(&call_metrics.module),
+        escape_field(&call_metrics.name),
+        call_metrics.call_hash.to_string(),
+        call_metrics.ncalls.to_string(),
+        call_metrics.timed_calls.to_string(),
+        call_metrics.time_ns.to_string(),
+        call_metrics.time_sq_ns.to_string(),
+        call_metrics.npanics.to_string(),
+        call_metrics.ok_calls.to_string(),
+        call_metrics.ok_time_ns.to_string(),
+        call_metrics.err_calls.to_string(),
+        call_metrics.err_time_ns.to_string(),
+    ];
+    format!("{}\n", fields.join("\t"))
+}
+
+fn parse_call_metrics(line: &str) -> Option<CallableMetrics> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != BASELINE_FIELDS {
+        return None;
+    }
+    let mut call_metrics = CallableMetrics::new(
+        unescape_field(fields[1])?,
+        unescape_field(fields[0])?,
+        fields[2].parse().ok()?,
+        fields[3].parse().ok()?,
+        fields[5].parse().ok()?,
+    );
+    call_metrics.timed_calls = fields[4].parse().ok()?;
+    call_metrics.time_sq_ns = fields[6].parse().ok()?;
+    call_metrics.npanics = fields[7].parse().ok()?;
+    call_metrics.ok_calls = fields[8].parse().ok()?;
+    call_metrics.ok_time_ns = fields[9].parse().ok()?;
+    call_metrics.err_calls = fields[10].parse().ok()?;
+    call_metrics.err_time_ns = fields[11].parse().ok()?;
+    Some(call_metrics)
+}
+
+fn escape_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape_field(field: &str) -> Option<String> {
+    let mut unescaped: String = String::new();
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            't' => unescaped.push('\t'),
+            'n' => unescaped.push('\n'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
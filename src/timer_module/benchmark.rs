@@ -0,0 +1,283 @@
+use std::hint::black_box;
+use std::time::Duration;
+
+use super::metrics::TimeFormatterNs;
+use super::recorder;
+use super::timer::TimerModule;
+
+const BOOTSTRAP_RESAMPLES: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Outliers {
+    pub low_severe: usize,
+    pub low_mild: usize,
+    pub high_mild: usize,
+    pub high_severe: usize,
+}
+
+impl Outliers {
+    pub fn get_count(&self) -> usize {
+        self.low_severe + self.low_mild + self.high_mild + self.high_severe
+    }
+
+    // Tukey's fences: samples beyond 1.5 IQR of the quartiles are mild
+    // outliers, beyond 3 IQR severe ones.
+    fn classify(sorted_samples: &[f64]) -> Outliers {
+        let q1: f64 = percentile(sorted_samples, 0.25);
+        let q3: f64 = percentile(sorted_samples, 0.75);
+        let iqr: f64 = q3 - q1;
+
+        let mut outliers: Outliers = Outliers::default();
+        for sample in sorted_samples.iter() {
+            if *sample < q1 - 3.0 * iqr {
+                outliers.low_severe += 1;
+            } else if *sample < q1 - 1.5 * iqr {
+                outliers.low_mild += 1;
+            } else if *sample > q3 + 3.0 * iqr {
+                outliers.high_severe += 1;
+            } else if *sample > q3 + 1.5 * iqr {
+                outliers.high_mild += 1;
+            }
+        }
+        outliers
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchmarkSummary {
+    pub name: String,
+    pub iterations: u64,
+    pub samples: Vec<f64>,
+    pub mean_ns: f64,
+    pub median_ns: f64,
+    pub stddev_ns: f64,
+    pub min_ns: f64,
+    pub max_ns: f64,
+    pub confidence_level: f64,
+    pub mean_lower_ns: f64,
+    pub mean_upper_ns: f64,
+    pub outliers: Outliers,
+}
+
+impl BenchmarkSummary {
+    fn new(
+        name: &str,
+        iterations: u64,
+        samples: Vec<f64>,
+        confidence_level: f64,
+        seed: u64,
+    ) -> Self {
+        let mut sorted_samples: Vec<f64> = samples.clone();
+        sorted_samples.sort_by(f64::total_cmp);
+
+        let mean_ns: f64 = mean(&samples);
+        let (mean_lower_ns, mean_upper_ns) =
+            bootstrap_mean_interval(&samples, confidence_level, seed);
+        BenchmarkSummary {
+            name: name.to_string(),
+            iterations,
+            mean_ns,
+            median_ns: percentile(&sorted_samples, 0.5),
+            stddev_ns: stddev(&samples, mean_ns),
+            min_ns: sorted_samples[0],
+            max_ns: sorted_samples[sorted_samples.len() - 1],
+            confidence_level,
+            mean_lower_ns,
+            mean_upper_ns,
+            outliers: Outliers::classify(&sorted_samples),
+            samples,
+        }
+    }
+
+    pub fn get_string(&self) -> String {
+        let format = |nanos: f64| TimeFormatterNs::new(nanos).auto_format();
+        let outliers: usize = self.outliers.get_count();
+        let outliers_prc: f64 = outliers as f64 / self.samples.len() as f64 * 100.0;
+        let mut string = format!(
+            "Benchmark: {}\nTime: [{} {} {}] ({:.0}% CI) — Median: [{}] — StdDev: [{}]\n",
+            self.name,
+            format(self.mean_lower_ns),
+            format(self.mean_ns),
+            format(self.mean_upper_ns),
+            self.confidence_level * 100.0,
+            format(self.median_ns),
+            format(self.stddev_ns)
+        );
+        string += &format!(
+            "Samples: [{}] — Iterations: [{}] — Outliers: [{} ({:.2}%)]",
+            self.samples.len(),
+            self.iterations,
+            outliers,
+            outliers_prc
+        );
+
+        let counts = [
+            (self.outliers.low_severe, "low severe"),
+            (self.outliers.low_mild, "low mild"),
+            (self.outliers.high_mild, "high mild"),
+            (self.outliers.high_severe, "high severe"),
+        ];
+        for (count, label) in counts.iter().filter(|(count, _)| *count > 0) {
+            string += &format!("\n  {} {}", count, label);
+        }
+        string
+    }
+}
+
+pub struct Benchmark {
+    name: String,
+    warm_up_time: Duration,
+    measurement_time: Duration,
+    sample_count: usize,
+    confidence_level: f64,
+    seed: Option<u64>,
+}
+
+impl Benchmark {
+    pub fn new(name: &str) -> Self {
+        Benchmark {
+            name: name.to_string(),
+            warm_up_time: Duration::from_secs(1),
+            measurement_time: Duration::from_secs(3),
+            sample_count: 100,
+            confidence_level: 0.95,
+            seed: None,
+        }
+    }
+
+    pub fn set_warm_up_time(&mut self, warm_up_time: Duration) -> &mut Self {
+        self.warm_up_time = warm_up_time;
+        self
+    }
+
+    pub fn set_measurement_time(&mut self, measurement_time: Duration) -> &mut Self {
+        self.measurement_time = measurement_time;
+        self
+    }
+
+    pub fn set_sample_count(&mut self, sample_count: usize) -> &mut Self {
+        assert!(sample_count > 1, "Benchmark needs at least two samples");
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn set_confidence_level(&mut self, confidence_level: f64) -> &mut Self {
+        assert!(
+            confidence_level > 0.0 && confidence_level < 1.0,
+            "Confidence level must be in (0, 1)"
+        );
+        self.confidence_level = confidence_level;
+        self
+    }
+
+    /// Seeds the bootstrap resampling, so the same samples always give the
+    /// same confidence interval.
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Summarizes per-call times measured elsewhere, e.g. saved samples.
+    pub fn summarize(&self, samples: Vec<f64>, iterations: u64) -> BenchmarkSummary {
+        assert!(samples.len() > 1, "Benchmark needs at least two samples");
+        let seed: u64 = self.seed.unwrap_or_else(recorder::random_seed);
+        BenchmarkSummary::new(&self.name, iterations, samples, self.confidence_level, seed)
+    }
+
+    pub fn run<F, R>(&self, mut function: F) -> BenchmarkSummary
+    where
+        F: FnMut() -> R,
+    {
+        let percall_ns: f64 = self.warm_up(&mut function);
+        let sample_ns: f64 = self.measurement_time.as_nanos() as f64 / self.sample_count as f64;
+        let sample_iterations: u64 = (sample_ns / percall_ns).ceil().max(1.0) as u64;
+
+        let mut timer_module: TimerModule = TimerModule::new();
+        let mut samples: Vec<f64> = Vec::with_capacity(self.sample_count);
+        for _ in 0..self.sample_count {
+            timer_module.reset().start();
+            for _ in 0..sample_iterations {
+                black_box(function());
+            }
+            let elapsed_ns: f64 = timer_module.get_duration().as_nanos() as f64;
+            samples.push(elapsed_ns / sample_iterations as f64);
+        }
+
+        let iterations: u64 = sample_iterations * self.sample_count as u64;
+        self.summarize(samples, iterations)
+    }
+
+    pub fn run_with_input<I, F, R>(&self, input: I, mut function: F) -> BenchmarkSummary
+    where
+        F: FnMut(&I) -> R,
+    {
+        self.run(|| function(black_box(&input)))
+    }
+
+    // Runs the function in doubling batches until the warm-up time is spent,
+    // and returns the observed time per call.
+    fn warm_up<F, R>(&self, function: &mut F) -> f64
+    where
+        F: FnMut() -> R,
+    {
+        let mut timer_module: TimerModule = TimerModule::new();
+        let mut iterations: u64 = 0;
+        let mut batch: u64 = 1;
+        timer_module.start();
+        loop {
+            for _ in 0..batch {
+                black_box(function());
+            }
+            iterations += batch;
+            batch *= 2;
+
+            let elapsed_time: Duration = timer_module.get_duration();
+            if elapsed_time >= self.warm_up_time {
+                return (elapsed_time.as_nanos() as f64 / iterations as f64).max(1.0);
+            }
+        }
+    }
+}
+
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+fn stddev(samples: &[f64], mean_ns: f64) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let squares: f64 = samples
+        .iter()
+        .map(|sample| (sample - mean_ns).powi(2))
+        .sum();
+    (squares / (samples.len() - 1) as f64).sqrt()
+}
+
+fn percentile(sorted_samples: &[f64], quantile: f64) -> f64 {
+    let rank: f64 = quantile * (sorted_samples.len() - 1) as f64;
+    let lower: usize = rank.floor() as usize;
+    let upper: usize = rank.ceil() as usize;
+    let fraction: f64 = rank - lower as f64;
+    sorted_samples[lower] + (sorted_samples[upper] - sorted_samples[lower]) * fraction
+}
+
+// Percentile bootstrap: resample the samples with replacement and take the
+// spread of the resampled means.
+fn bootstrap_mean_interval(samples: &[f64], confidence_level: f64, seed: u64) -> (f64, f64) {
+    let mut state: u64 = seed;
+    let mut means: Vec<f64> = Vec::with_capacity(BOOTSTRAP_RESAMPLES);
+    for _ in 0..BOOTSTRAP_RESAMPLES {
+        let mut sum: f64 = 0.0;
+        for _ in 0..samples.len() {
+            let random: f64 = recorder::next_seeded_random(&mut state);
+            let index: usize = (random * samples.len() as f64) as usize;
+            sum += samples[index];
+        }
+        means.push(sum / samples.len() as f64);
+    }
+    means.sort_by(f64::total_cmp);
+
+    let tail: f64 = (1.0 - confidence_level) / 2.0;
+    (percentile(&means, tail), percentile(&means, 1.0 - tail))
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::metrics::{CallableMetrics, TimeFormatterNs};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetLimit {
+    PerCall(Duration),
+    Total(Duration),
+}
+
+impl BudgetLimit {
+    pub fn get_limit(&self) -> Duration {
+        match self {
+            BudgetLimit::PerCall(limit) | BudgetLimit::Total(limit) => *limit,
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        match self {
+            BudgetLimit::PerCall(_) => "per call",
+            BudgetLimit::Total(_) => "total",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetStatus {
+    Passed,
+    Exceeded,
+    Missing,
+}
+
+#[derive(Clone, Debug)]
+pub struct BudgetResult {
+    pub name: String,
+    pub limit: BudgetLimit,
+    pub actual_ns: Option<f64>,
+    pub status: BudgetStatus,
+}
+
+impl BudgetResult {
+    pub fn get_string(&self) -> String {
+        let limit = TimeFormatterNs::new_from_duration(self.limit.get_limit()).auto_format();
+        let label = self.limit.get_label();
+        let actual_ns: f64 = match self.actual_ns {
+            Some(actual_ns) => actual_ns,
+            None => return format!("{} {}: never called — budget [{}]", self.name, label, limit),
+        };
+        let actual = TimeFormatterNs::new(actual_ns).auto_format();
+        let status = match self.status {
+            BudgetStatus::Passed => "passed",
+            _ => "exceeded",
+        };
+        format!(
+            "{} {}: [{}] — budget [{}] — {}",
+            self.name, label, actual, limit, status
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BudgetReport {
+    pub results: Vec<BudgetResult>,
+}
+
+impl BudgetReport {
+    pub fn is_passed(&self) -> bool {
+        self.get_failures().is_empty()
+    }
+
+    pub fn get_failures(&self) -> Vec<&BudgetResult> {
+        self.results
+            .iter()
+            .filter(|result| result.status != BudgetStatus::Passed)
+            .collect()
+    }
+
+    pub fn get_string(&self) -> String {
+        let lines: Vec<String> = self.results.iter().map(|r| r.get_string()).collect();
+        lines.join("\n")
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PerformanceBudget {
+    limits: Vec<(String, BudgetLimit)>,
+}
+
+impl PerformanceBudget {
+    pub fn new() -> Self {
+        PerformanceBudget { limits: Vec::new() }
+    }
+
+    pub fn add_limit(&mut self, name: &str, limit: BudgetLimit) -> &mut Self {
+        self.limits.push((name.to_string(), limit));
+        self
+    }
+
+    pub fn add_percall_limit(&mut self, name: &str, limit: Duration) -> &mut Self {
+        self.add_limit(name, BudgetLimit::PerCall(limit))
+    }
+
+    pub fn add_total_limit(&mut self, name: &str, limit: Duration) -> &mut Self {
+        self.add_limit(name, BudgetLimit::Total(limit))
+    }
+
+    /// Checks every limit. A name matches callables by name or by a trailing
+    /// part of `module::name`; all matching callables are counted together.
+    /// Wrapped closures are named `{{closure}}`, so give them a budget through
+    /// the key of `keyed_function_wrapper` or the name of `#[profile]`. A
+    /// budget for a callable that was never called fails as `Missing`.
+    pub fn check(&self, callable_metrics: &HashMap<u64, CallableMetrics>) -> BudgetReport {
+        let mut results: Vec<BudgetResult> = Vec::new();
+        for (name, limit) in self.limits.iter() {
+            let (ncalls, time_ns) = callable_metrics
+                .values()
+                .filter(|call_metrics| call_metrics.matches_name(name))
+                .fold((0, 0.0), |(ncalls, time_ns), call_metrics| {
+                    (ncalls + call_metrics.ncalls, time_ns + call_metrics.time_ns)
+                });
+
+            let actual_ns: Option<f64> = match limit {
+                _ if ncalls == 0 => None,
+                BudgetLimit::PerCall(_) => Some(time_ns / ncalls as f64),
+                BudgetLimit::Total(_) => Some(time_ns),
+            };
+            let status: BudgetStatus = match actual_ns {
+                None => BudgetStatus::Missing,
+                Some(actual_ns) if actual_ns < limit.get_limit().as_nanos() as f64 => {
+                    BudgetStatus::Passed
+                }
+                Some(_) => BudgetStatus::Exceeded,
+            };
+            results.push(BudgetResult {
+                name: name.clone(),
+                limit: *limit,
+                actual_ns,
+                status,
+            });
+        }
+        BudgetReport { results }
+    }
+}
+
+/// Panics with the budget report when a profiler exceeds its budget.
+///
+/// ```ignore
+/// assert_budget!(profiler, &budget);
+/// assert_budget!(profiler, "binary_search_value", per_call < Duration::from_nanos(500));
+/// assert_budget!(profiler, "generate_binary_combinations", total < Duration::from_millis(10));
+/// ```
+#[macro_export]
+macro_rules! assert_budget {
+    ($profiler:expr, $budget:expr) => {{
+        let report = $profiler.check_budget($budget);
+        if !report.is_passed() {
+            panic!("performance budget exceeded:\n{}", report.get_string());
+        }
+    }};
+    ($profiler:expr, $name:expr, per_call < $limit:expr) => {{
+        let mut budget = $crate::budget::PerformanceBudget::new();
+        budget.add_percall_limit($name, $limit);
+        $crate::assert_budget!($profiler, &budget);
+    }};
+    ($profiler:expr, $name:expr, total < $limit:expr) => {{
+        let mut budget = $crate::budget::PerformanceBudget::new();
+        budget.add_total_limit($name, $limit);
+        $crate::assert_budget!($profiler, &budget);
+    }};
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::process;
+use std::ptr;
+
+use super::metrics::{CallableMetrics, ProfileMetricsReport};
+use super::terminal::ANSICode;
+use super::theme::ReportTheme;
+
+const NORMAL_QUANTILE_95: f64 = 1.959964;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Regressed,
+    Improved,
+    Unchanged,
+    Added,
+    Removed,
+}
+
+impl Verdict {
+    pub fn get_label(&self) -> &str {
+        match self {
+            Verdict::Regressed => "regressed",
+            Verdict::Improved => "improved",
+            Verdict::Unchanged => "unchanged",
+            Verdict::Added => "added",
+            Verdict::Removed => "removed",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CallComparison {
+    pub name: String,
+    pub module: String,
+    pub baseline: Option<CallableMetrics>,
+    pub current: Option<CallableMetrics>,
+    pub verdict: Verdict,
+    pub ambiguous: bool,
+}
+
+impl CallComparison {
+    fn new(
+        baseline: Option<CallableMetrics>,
+        current: Option<CallableMetrics>,
+        noise_threshold_prc: f64,
+    ) -> CallComparison {
+        let call_metrics: &CallableMetrics = current.as_ref().or(baseline.as_ref()).unwrap();
+        let mut call_comparison = CallComparison {
+            name: call_metrics.name.clone(),
+            module: call_metrics.module.clone(),
+            baseline,
+            current,
+            verdict: Verdict::Unchanged,
+            ambiguous: false,
+        };
+        call_comparison.verdict = call_comparison.get_verdict(noise_threshold_prc);
+        call_comparison
+    }
+
+    pub fn get_delta_ns(&self) -> Option<f64> {
+        let (baseline, current) = (self.baseline.as_ref()?, self.current.as_ref()?);
+        Some(current.get_percall_time() - baseline.get_percall_time())
+    }
+
+    pub fn get_change_prc(&self) -> Option<f64> {
+        let baseline_ns: f64 = self.baseline.as_ref()?.get_percall_time();
+        if baseline_ns <= 0.0 {
+            return None;
+        }
+        Some(self.get_delta_ns()? / baseline_ns * 100.0)
+    }
+
+    // A change counts once it is both larger than the noise threshold and
+    // significant under Welch's t-test at the 95% level.
+    fn get_verdict(&self, noise_threshold_prc: f64) -> Verdict {
+        let (baseline, current) = match (&self.baseline, &self.current) {
+            (Some(baseline), Some(current)) => (baseline, current),
+            (None, _) => return Verdict::Added,
+            (_, None) => return Verdict::Removed,
+        };
+        let change_prc: f64 = match self.get_change_prc() {
+            Some(change_prc) => change_prc,
+            None => return Verdict::Unchanged,
+        };
+        if change_prc.abs() <= noise_threshold_prc || !is_significant(baseline, current) {
+            return Verdict::Unchanged;
+        }
+        if change_prc > 0.0 {
+            return Verdict::Regressed;
+        }
+        Verdict::Improved
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BaselineComparison {
+    pub calls: Vec<CallComparison>,
+    pub noise_threshold_prc: f64,
+}
+
+impl BaselineComparison {
+    pub fn get_regressions(&self, threshold_prc: f64) -> Vec<&CallComparison> {
+        self.calls
+            .iter()
+            .filter(|call| call.verdict == Verdict::Regressed)
+            .filter(|call| call.get_change_prc().unwrap_or(0.0) > threshold_prc)
+            .collect()
+    }
+
+    /// Callables left unmatched because their module and name belong to
+    /// several callables on one side, e.g. closures in the same function.
+    pub fn get_ambiguous(&self) -> Vec<&CallComparison> {
+        self.calls.iter().filter(|call| call.ambiguous).collect()
+    }
+
+    pub fn has_regression(&self, threshold_prc: f64) -> bool {
+        !self.get_regressions(threshold_prc).is_empty()
+    }
+
+    /// Exits the process with status 1 when any callable regressed by more
+    /// than `threshold_prc` percent, for failing CI jobs.
+    pub fn exit_on_regression(&self, threshold_prc: f64) {
+        let regressions: Vec<&CallComparison> = self.get_regressions(threshold_prc);
+        if regressions.is_empty() {
+            return;
+        }
+        for call in regressions.iter() {
+            let change_prc: f64 = call.get_change_prc().unwrap_or(0.0);
+            eprintln!("Regression: {} ({:+.2}%)", call.name, change_prc);
+        }
+        process::exit(1);
+    }
+
+    pub(crate) fn write_report(&self, metrics_report: &mut ProfileMetricsReport) {
+        metrics_report.write_header("BASELINE COMPARISON");
+        for call_comparison in self.calls.iter() {
+            let string: String = format_call_comparison(metrics_report, call_comparison);
+            let color: Option<ANSICode> = match call_comparison.verdict {
+                Verdict::Regressed => Some(ANSICode::Red),
+                Verdict::Improved => Some(ANSICode::Green),
+                _ => None,
+            };
+            metrics_report.write_entry(string, color);
+        }
+
+        let theme: &ReportTheme = metrics_report.get_theme();
+        let regressions = self.get_regressions(self.noise_threshold_prc);
+        let noise: String = theme.format_decimal(&format!("{:.2}", self.noise_threshold_prc));
+        let mut total: String = format!(
+            "Regressions: [{}]{}Noise Threshold: [{}%]",
+            regressions.len(),
+            theme.field_separator,
+            noise
+        );
+        let ambiguous: usize = self.get_ambiguous().len();
+        if ambiguous > 0 {
+            total += &format!("{}Ambiguous: [{}]", theme.field_separator, ambiguous);
+        }
+        metrics_report.write_total(&total);
+    }
+}
+
+// Calls are first paired by call hash, which stays the same between runs for
+// an unchanged identity. The rest are paired by module and name, unless the
+// name is shared by several remaining callables on either side; those are
+// left unmatched and marked ambiguous rather than compared at random.
+pub(crate) fn compare_calls(
+    baseline_calls: &[CallableMetrics],
+    callable_metrics: &HashMap<u64, CallableMetrics>,
+    noise_threshold_prc: f64,
+) -> BaselineComparison {
+    let mut baseline_calls: Vec<Option<&CallableMetrics>> =
+        baseline_calls.iter().map(Some).collect();
+    let mut current_calls: Vec<&CallableMetrics> = callable_metrics.values().collect();
+    current_calls
+        .sort_by(|a, b| (&a.module, &a.name, a.call_hash).cmp(&(&b.module, &b.name, b.call_hash)));
+
+    let mut matches: Vec<(Option<&CallableMetrics>, &CallableMetrics)> = Vec::new();
+    let mut unmatched_calls: Vec<&CallableMetrics> = Vec::new();
+    for current in current_calls {
+        let baseline: Option<&CallableMetrics> = baseline_calls
+            .iter_mut()
+            .find(|baseline| {
+                baseline.is_some_and(|baseline| {
+                    baseline.call_hash == current.call_hash && baseline == current
+                })
+            })
+            .and_then(|baseline| baseline.take());
+        match baseline {
+            Some(baseline) => matches.push((Some(baseline), current)),
+            None => unmatched_calls.push(current),
+        }
+    }
+
+    let mut ambiguous_calls: Vec<&CallableMetrics> = Vec::new();
+    for current in unmatched_calls.iter() {
+        let same_name = |call: &&&CallableMetrics| **call == *current;
+        let current_count: usize = unmatched_calls.iter().filter(same_name).count();
+        let baseline_count: usize = baseline_calls.iter().flatten().filter(same_name).count();
+        if current_count == 1 && baseline_count == 1 {
+            let baseline: Option<&CallableMetrics> = baseline_calls
+                .iter_mut()
+                .find(|baseline| baseline.is_some_and(|baseline| baseline == *current))
+                .and_then(|baseline| baseline.take());
+            matches.push((baseline, current));
+            continue;
+        }
+        if baseline_count > 0 {
+            ambiguous_calls.push(current);
+        }
+        matches.push((None, current));
+    }
+
+    let mut calls: Vec<CallComparison> = Vec::new();
+    for (baseline, current) in matches {
+        let mut call_comparison: CallComparison = CallComparison::new(
+            baseline.cloned(),
+            Some(current.clone()),
+            noise_threshold_prc,
+        );
+        call_comparison.ambiguous = ambiguous_calls.iter().any(|call| ptr::eq(*call, current));
+        calls.push(call_comparison);
+    }
+    for baseline in baseline_calls.into_iter().flatten() {
+        let mut call_comparison: CallComparison =
+            CallComparison::new(Some(baseline.clone()), None, noise_threshold_prc);
+        call_comparison.ambiguous = ambiguous_calls.contains(&baseline);
+        calls.push(call_comparison);
+    }
+    BaselineComparison {
+        calls,
+        noise_threshold_prc,
+    }
+}
+
+fn format_call_comparison(
+    metrics_report: &ProfileMetricsReport,
+    call_comparison: &CallComparison,
+) -> String {
+    let theme: &ReportTheme = metrics_report.get_theme();
+    let (ls, fs) = (&theme.line_separator, &theme.field_separator);
+    let format_percall = |call_metrics: &Option<CallableMetrics>| match call_metrics {
+        Some(call_metrics) => metrics_report.format_time(call_metrics.get_percall_time()),
+        None => "-".to_string(),
+    };
+    let mut string = format!(
+        "Name: {}{}Baseline: [{}]{}Current: [{}]",
+        call_comparison.name,
+        ls,
+        format_percall(&call_comparison.baseline),
+        fs,
+        format_percall(&call_comparison.current)
+    );
+    if let Some(delta_ns) = call_comparison.get_delta_ns() {
+        let sign = if delta_ns < 0.0 { "-" } else { "+" };
+        let delta = metrics_report.format_time(delta_ns.abs());
+        string += &format!("{}Delta: [{}{}]", fs, sign, delta);
+    }
+    if let Some(change_prc) = call_comparison.get_change_prc() {
+        let change = theme.format_decimal(&format!("{:+.2}", change_prc));
+        string += &format!("{}Change: [{}%]", fs, change);
+    }
+    let verdict = call_comparison.verdict.get_label();
+    string += &format!("{}Verdict: [{}]", fs, verdict);
+    if call_comparison.ambiguous {
+        string += " (ambiguous)";
+    }
+    string
+}
+
+fn is_significant(baseline: &CallableMetrics, current: &CallableMetrics) -> bool {
+    let (n1, n2) = (baseline.timed_calls as f64, current.timed_calls as f64);
+    if n1 < 1.0 || n2 < 1.0 {
+        return false;
+    }
+    let v1: f64 = baseline.get_percall_stddev().powi(2) / n1;
+    let v2: f64 = current.get_percall_stddev().powi(2) / n2;
+    let delta_ns: f64 = current.get_percall_time() - baseline.get_percall_time();
+    if v1 + v2 <= 0.0 {
+        return delta_ns != 0.0;
+    }
+
+    // Welch–Satterthwaite degrees of freedom, with the 97.5% quantile of
+    // Student's t approximated by a Cornish-Fisher expansion.
+    let mut df_denominator: f64 = 0.0;
+    if n1 > 1.0 {
+        df_denominator += v1 * v1 / (n1 - 1.0);
+    }
+    if n2 > 1.0 {
+        df_denominator += v2 * v2 / (n2 - 1.0);
+    }
+    let df: f64 = (v1 + v2).powi(2) / df_denominator;
+    let z: f64 = NORMAL_QUANTILE_95;
+    let critical_t: f64 = z
+        + (z.powi(3) + z) / (4.0 * df)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * df * df);
+    delta_ns.abs() / (v1 + v2).sqrt() > critical_t
+}
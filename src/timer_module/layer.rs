@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use tracing::callsite::Identifier;
+use tracing::span::{Attributes, Id};
+use tracing::{Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+use super::profiler::{TimeProfiler, PROFILING_ENABLED};
+use super::recorder::CallCounters;
+
+// A span entered on several threads at once is timed on each of them, so
+// its busy time is the sum over threads and can exceed the wall time.
+#[derive(Default)]
+struct SpanTiming {
+    busy: Duration,
+    entered: HashMap<ThreadId, Duration>,
+    entries: u64,
+}
+
+/// Records `tracing` spans with a `TimeProfiler`. Each span is one call,
+/// timed by the time spent inside it across all its enters, and recorded
+/// when it closes beneath the outermost span it was opened in.
+///
+/// ```ignore
+/// tracing_subscriber::registry()
+///     .with(ProfilerLayer::global())
+///     .init();
+/// ```
+pub struct ProfilerLayer {
+    // Without a profiler of its own the layer records with the global one.
+    profiler: Option<Arc<TimeProfiler>>,
+    call_refs: RwLock<HashMap<Identifier, (u64, Arc<CallCounters>)>>,
+}
+
+impl ProfilerLayer {
+    pub fn new(profiler: Arc<TimeProfiler>) -> Self {
+        ProfilerLayer {
+            profiler: Some(profiler),
+            call_refs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// A layer recording with `TimeProfiler::global()`.
+    pub fn global() -> Self {
+        ProfilerLayer {
+            profiler: None,
+            call_refs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn get_profiler(&self) -> &TimeProfiler {
+        match &self.profiler {
+            Some(profiler) => profiler,
+            None => TimeProfiler::global(),
+        }
+    }
+
+    fn get_call_ref(&self, metadata: &'static Metadata<'static>) -> (u64, Arc<CallCounters>) {
+        let callsite: Identifier = metadata.callsite();
+        if let Some((call_hash, counters)) = self.call_refs.read().unwrap().get(&callsite) {
+            return (*call_hash, counters.clone());
+        }
+
+        let module: &str = metadata.module_path().unwrap_or(metadata.target());
+        let call_ref = self.get_profiler().add_named_ref(metadata.name(), module);
+        let mut call_refs = self.call_refs.write().unwrap();
+        call_refs.entry(callsite).or_insert(call_ref).clone()
+    }
+}
+
+impl Default for ProfilerLayer {
+    fn default() -> Self {
+        Self::global()
+    }
+}
+
+impl<S> Layer<S> for ProfilerLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !PROFILING_ENABLED {
+            return;
+        }
+        let span = ctx.span(id).expect("span not found in registry");
+        span.extensions_mut().insert(SpanTiming::default());
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        let Some(timing) = extensions.get_mut::<SpanTiming>() else {
+            return;
+        };
+        // A root span is a primary call from its first enter, like a wrapped
+        // call, so the spans beneath a long-lived root are reported before
+        // it closes.
+        if timing.entries == 0 && span.parent().is_none() {
+            self.get_call_ref(span.metadata()).1.mark_primary();
+        }
+        let now: Duration = self.get_profiler().get_clock().now();
+        timing.entered.insert(thread::current().id(), now);
+        timing.entries += 1;
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<SpanTiming>() {
+            let now: Duration = self.get_profiler().get_clock().now();
+            if let Some(entered) = timing.entered.remove(&thread::current().id()) {
+                timing.busy += now.saturating_sub(entered);
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let timing: Option<SpanTiming> = span.extensions_mut().remove::<SpanTiming>();
+        let Some(timing) = timing else { return };
+        // A span that was never entered did not run, and is not registered.
+        if timing.entries == 0 {
+            return;
+        }
+
+        let pcall_hash: Option<u64> = span
+            .scope()
+            .from_root()
+            .next()
+            .filter(|root: &SpanRef<'_, S>| root.id() != id)
+            .map(|root| self.get_call_ref(root.metadata()).0);
+        let (call_hash, counters) = self.get_call_ref(span.metadata());
+        let callable_ref = (call_hash, counters.as_ref());
+        self.get_profiler()
+            .record_span(callable_ref, pcall_hash, timing.busy);
+    }
+}
@@ -1,65 +1,351 @@
 use super::terminal::ANSICode;
 use super::terminal::Terminal;
+use super::theme::ReportTheme;
+use super::window::WindowStatistics;
 
 use core::hash::Hasher;
 use std::collections::HashMap;
 use std::hash::Hash;
-use std::sync::MutexGuard;
 use std::time::Duration;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl TimeUnit {
+    pub fn get_nanos(&self) -> f64 {
+        match self {
+            TimeUnit::Days => 86_400e9,
+            TimeUnit::Hours => 3_600e9,
+            TimeUnit::Minutes => 60e9,
+            TimeUnit::Seconds => 1e9,
+            TimeUnit::Milliseconds => 1e6,
+            TimeUnit::Microseconds => 1e3,
+            TimeUnit::Nanoseconds => 1.0,
+        }
+    }
+
+    pub fn get_symbol(&self, ascii: bool) -> &str {
+        match self {
+            TimeUnit::Days => "d",
+            TimeUnit::Hours => "h",
+            TimeUnit::Minutes => "m",
+            TimeUnit::Seconds => "s",
+            TimeUnit::Milliseconds => "ms",
+            TimeUnit::Microseconds if ascii => "us",
+            TimeUnit::Microseconds => "μs",
+            TimeUnit::Nanoseconds => "ns",
+        }
+    }
+
+    pub fn from_nanos(nanos: f64) -> TimeUnit {
+        if nanos >= 86_400e9 {
+            return TimeUnit::Days;
+        } else if nanos >= 3_600e9 {
+            return TimeUnit::Hours;
+        } else if nanos >= 60e9 {
+            return TimeUnit::Minutes;
+        } else if nanos >= 1e9 {
+            return TimeUnit::Seconds;
+        } else if nanos >= 1e6 {
+            return TimeUnit::Milliseconds;
+        } else if nanos >= 1e3 {
+            return TimeUnit::Microseconds;
+        }
+        TimeUnit::Nanoseconds
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeStyle {
+    Unit,
+    Compound,
+    Clock,
+    Iso8601,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeFormat {
+    style: TimeStyle,
+    precision: usize,
+    unit: Option<TimeUnit>,
+    max_unit: TimeUnit,
+    ascii: bool,
+    trim_zeros: bool,
+}
+
+impl TimeFormat {
+    pub fn new() -> TimeFormat {
+        TimeFormat {
+            style: TimeStyle::Unit,
+            precision: 2,
+            unit: None,
+            max_unit: TimeUnit::Seconds,
+            ascii: false,
+            trim_zeros: false,
+        }
+    }
+
+    pub fn set_style(&mut self, style: TimeStyle) -> &mut Self {
+        self.style = style;
+        self
+    }
+
+    pub fn set_precision(&mut self, precision: usize) -> &mut Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn set_unit(&mut self, unit: TimeUnit) -> &mut Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    pub fn set_auto_unit(&mut self) -> &mut Self {
+        self.unit = None;
+        self
+    }
+
+    /// The largest unit the automatic unit picks. Defaults to seconds, so
+    /// long durations print as e.g. "125.30s" unless raised.
+    pub fn set_max_unit(&mut self, max_unit: TimeUnit) -> &mut Self {
+        self.max_unit = max_unit;
+        self
+    }
+
+    pub fn set_ascii(&mut self, ascii: bool) -> &mut Self {
+        self.ascii = ascii;
+        self
+    }
+
+    pub fn set_trim_zeros(&mut self, trim_zeros: bool) -> &mut Self {
+        self.trim_zeros = trim_zeros;
+        self
+    }
+
+    pub fn get_style(&self) -> TimeStyle {
+        self.style
+    }
+
+    pub fn get_precision(&self) -> usize {
+        self.precision
+    }
+
+    pub fn get_unit(&self) -> Option<TimeUnit> {
+        self.unit
+    }
+
+    pub fn get_max_unit(&self) -> TimeUnit {
+        self.max_unit
+    }
+
+    pub fn is_ascii(&self) -> bool {
+        self.ascii
+    }
+
+    pub fn is_trim_zeros(&self) -> bool {
+        self.trim_zeros
+    }
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct TimeFormatterNs {
     nanos: f64,
+    format: TimeFormat,
 }
 
 impl TimeFormatterNs {
     pub fn new(nanos: f64) -> TimeFormatterNs {
-        TimeFormatterNs { nanos }
+        let format: TimeFormat = TimeFormat::new();
+        TimeFormatterNs { nanos, format }
     }
 
     pub fn new_from_duration(duration: Duration) -> TimeFormatterNs {
         let nanos: f64 = duration.as_nanos() as f64;
-        TimeFormatterNs { nanos }
+        TimeFormatterNs::new(nanos)
+    }
+
+    pub fn with_format(mut self, format: TimeFormat) -> TimeFormatterNs {
+        self.format = format;
+        self
+    }
+
+    pub fn format_unit(&self, unit: TimeUnit) -> String {
+        let value: f64 = self.nanos / unit.get_nanos();
+        let mut number: String = format!("{:.*}", self.format.precision, value);
+        if self.format.trim_zeros {
+            number = trim_fraction_zeros(number);
+        }
+        format!("{}{}", number, unit.get_symbol(self.format.ascii))
+    }
+
+    pub fn format_compound(&self) -> String {
+        let precision: usize = self.format.precision.min(9);
+        let nanos: u128 = round_nanos(self.nanos.abs(), precision);
+        let (days, hours, minutes, secs_nanos) = split_nanos(nanos);
+
+        let mut seconds: String = format!("{:.*}", precision, secs_nanos as f64 / 1e9);
+        if self.format.trim_zeros {
+            seconds = trim_fraction_zeros(seconds);
+        }
+
+        let mut parts: Vec<String> = Vec::new();
+        if days > 0 {
+            parts.push(format!("{}d", days));
+        }
+        if !parts.is_empty() {
+            parts.push(format!("{:02}h", hours));
+        } else if hours > 0 {
+            parts.push(format!("{}h", hours));
+        }
+        if !parts.is_empty() {
+            parts.push(format!("{:02}m", minutes));
+        } else if minutes > 0 {
+            parts.push(format!("{}m", minutes));
+        }
+        if !parts.is_empty() && secs_nanos < 10_000_000_000 {
+            seconds = format!("0{}", seconds);
+        }
+        parts.push(format!("{}s", seconds));
+
+        format!("{}{}", sign_prefix(self.nanos), parts.join(" "))
+    }
+
+    pub fn format_clock(&self) -> String {
+        let nanos: u128 = round_nanos(self.nanos.abs(), 3);
+        let (days, hours, minutes, secs_nanos) = split_nanos(nanos);
+        let hours: u128 = days * 24 + hours;
+        let seconds: u128 = secs_nanos / 1_000_000_000;
+        let millis: u128 = secs_nanos % 1_000_000_000 / 1_000_000;
+
+        format!(
+            "{}{:02}:{:02}:{:02}.{:03}",
+            sign_prefix(self.nanos),
+            hours,
+            minutes,
+            seconds,
+            millis
+        )
+    }
+
+    pub fn format_iso8601(&self) -> String {
+        let nanos: u128 = self.nanos.abs().round() as u128;
+        let (days, hours, minutes, secs_nanos) = split_nanos(nanos);
+        let hours: u128 = days * 24 + hours;
+
+        let mut string: String = format!("{}PT", sign_prefix(self.nanos));
+        if hours > 0 {
+            string += &format!("{}H", hours);
+        }
+        if minutes > 0 {
+            string += &format!("{}M", minutes);
+        }
+        if secs_nanos > 0 || (hours == 0 && minutes == 0) {
+            let seconds: String = format!("{:.9}", secs_nanos as f64 / 1e9);
+            string += &format!("{}S", trim_fraction_zeros(seconds));
+        }
+        string
     }
 
     pub fn format_seconds(&self) -> String {
-        let secs: f64 = self.nanos / 1e9;
-        format!("{:.2}s", secs)
+        self.format_unit(TimeUnit::Seconds)
     }
 
     pub fn format_milliseconds(&self) -> String {
-        let millis: f64 = self.nanos / 1e6;
-        format!("{:.2}ms", millis)
+        self.format_unit(TimeUnit::Milliseconds)
     }
 
     pub fn format_microseconds(&self) -> String {
-        let micros: f64 = self.nanos / 1e3;
-        format!("{:.2}μs", micros)
+        self.format_unit(TimeUnit::Microseconds)
     }
 
     pub fn format_nanoseconds(&self) -> String {
-        format!("{:.2}ns", self.nanos)
+        self.format_unit(TimeUnit::Nanoseconds)
     }
 
     pub fn auto_format(&self) -> String {
-        let nanos: f64 = self.nanos;
-        if nanos >= 1e9 {
-            return self.format_seconds();
-        } else if nanos >= 1e6 {
-            return self.format_milliseconds();
-        } else if nanos >= 1e3 {
-            return self.format_microseconds();
+        match self.format.style {
+            TimeStyle::Compound => return self.format_compound(),
+            TimeStyle::Clock => return self.format_clock(),
+            TimeStyle::Iso8601 => return self.format_iso8601(),
+            TimeStyle::Unit => {}
+        }
+        if let Some(unit) = self.format.unit {
+            return self.format_unit(unit);
         }
-        self.format_nanoseconds()
+        let mut unit: TimeUnit = TimeUnit::from_nanos(self.nanos);
+        if unit.get_nanos() > self.format.max_unit.get_nanos() {
+            unit = self.format.max_unit;
+        }
+        self.format_unit(unit)
+    }
+}
+
+fn trim_fraction_zeros(number: String) -> String {
+    if !number.contains('.') {
+        return number;
+    }
+    let trimmed: &str = number.trim_end_matches('0').trim_end_matches('.');
+    trimmed.to_string()
+}
+
+fn sign_prefix(nanos: f64) -> &'static str {
+    if nanos < 0.0 {
+        return "-";
     }
+    ""
 }
 
-#[derive(Debug)]
+fn round_nanos(nanos: f64, precision: usize) -> u128 {
+    let step: f64 = 10f64.powi(9 - precision as i32);
+    ((nanos / step).round() * step) as u128
+}
+
+fn split_nanos(nanos: u128) -> (u128, u128, u128, u128) {
+    let minute_nanos: u128 = 60 * 1_000_000_000;
+    let total_minutes: u128 = nanos / minute_nanos;
+    let secs_nanos: u128 = nanos % minute_nanos;
+    let days: u128 = total_minutes / (24 * 60);
+    let hours: u128 = total_minutes / 60 % 24;
+    let minutes: u128 = total_minutes % 60;
+    (days, hours, minutes, secs_nanos)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallOutcome {
+    Returned,
+    Ok,
+    Err,
+    Panicked,
+}
+
+#[derive(Clone, Debug)]
 pub struct CallableMetrics {
     pub name: String,
     pub module: String,
     pub call_hash: u64,
     pub ncalls: usize,
+    pub timed_calls: usize,
     pub time_ns: f64,
+    pub time_sq_ns: f64,
+    pub npanics: usize,
+    pub ok_calls: usize,
+    pub ok_time_ns: f64,
+    pub err_calls: usize,
+    pub err_time_ns: f64,
+    pub windows: Vec<WindowStatistics>,
 }
 
 impl CallableMetrics {
@@ -75,7 +361,15 @@ impl CallableMetrics {
             module,
             call_hash,
             ncalls,
+            timed_calls: ncalls,
             time_ns,
+            time_sq_ns: 0.0,
+            npanics: 0,
+            ok_calls: 0,
+            ok_time_ns: 0.0,
+            err_calls: 0,
+            err_time_ns: 0.0,
+            windows: Vec::new(),
         }
     }
 
@@ -90,6 +384,73 @@ impl CallableMetrics {
         call_metrics
     }
 
+    pub fn record(&mut self, time_ns: f64, outcome: CallOutcome) {
+        self.time_ns += time_ns;
+        self.time_sq_ns += time_ns * time_ns;
+        self.ncalls += 1;
+        self.timed_calls += 1;
+        match outcome {
+            CallOutcome::Returned => {}
+            CallOutcome::Ok => {
+                self.ok_calls += 1;
+                self.ok_time_ns += time_ns;
+            }
+            CallOutcome::Err => {
+                self.err_calls += 1;
+                self.err_time_ns += time_ns;
+            }
+            CallOutcome::Panicked => self.npanics += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &CallableMetrics) {
+        self.ncalls += other.ncalls;
+        self.timed_calls += other.timed_calls;
+        self.time_ns += other.time_ns;
+        self.time_sq_ns += other.time_sq_ns;
+        self.npanics += other.npanics;
+        self.ok_calls += other.ok_calls;
+        self.ok_time_ns += other.ok_time_ns;
+        self.err_calls += other.err_calls;
+        self.err_time_ns += other.err_time_ns;
+    }
+
+    /// Metrics recorded between `earlier` and this, for two readings of the
+    /// same callable.
+    pub fn diff(&self, earlier: &CallableMetrics) -> CallableMetrics {
+        let mut call_metrics: CallableMetrics = self.clone();
+        call_metrics.ncalls = self.ncalls.saturating_sub(earlier.ncalls);
+        call_metrics.timed_calls = self.timed_calls.saturating_sub(earlier.timed_calls);
+        call_metrics.time_ns = (self.time_ns - earlier.time_ns).max(0.0);
+        call_metrics.time_sq_ns = (self.time_sq_ns - earlier.time_sq_ns).max(0.0);
+        call_metrics.npanics = self.npanics.saturating_sub(earlier.npanics);
+        call_metrics.ok_calls = self.ok_calls.saturating_sub(earlier.ok_calls);
+        call_metrics.ok_time_ns = (self.ok_time_ns - earlier.ok_time_ns).max(0.0);
+        call_metrics.err_calls = self.err_calls.saturating_sub(earlier.err_calls);
+        call_metrics.err_time_ns = (self.err_time_ns - earlier.err_time_ns).max(0.0);
+        call_metrics
+    }
+
+    /// Matches the bare name, `module::name`, or `module::name` with
+    /// leading modules left out, e.g. `parser::parse` for `app::parser::parse`.
+    pub fn matches_name(&self, name: &str) -> bool {
+        if self.name == name {
+            return true;
+        }
+        let module: &str = match name
+            .strip_suffix(self.name.as_str())
+            .and_then(|module| module.strip_suffix("::"))
+        {
+            Some(module) => module,
+            None => return false,
+        };
+        module == self.module || self.module.ends_with(&format!("::{}", module))
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.timed_calls < self.ncalls
+    }
+
     pub fn get_percall_time(&self) -> f64 {
         if self.ncalls > 0 {
             let percall_time_ns = self.time_ns / self.ncalls as f64;
@@ -97,6 +458,37 @@ impl CallableMetrics {
         }
         0.0
     }
+
+    pub fn get_percall_stddev(&self) -> f64 {
+        if self.ncalls < 2 {
+            return 0.0;
+        }
+        let ncalls = self.ncalls as f64;
+        let variance = (self.time_sq_ns - self.time_ns * self.time_ns / ncalls) / (ncalls - 1.0);
+        variance.max(0.0).sqrt()
+    }
+
+    pub fn get_ok_percall_time(&self) -> f64 {
+        if self.ok_calls > 0 {
+            return self.ok_time_ns / self.ok_calls as f64;
+        }
+        0.0
+    }
+
+    pub fn get_err_percall_time(&self) -> f64 {
+        if self.err_calls > 0 {
+            return self.err_time_ns / self.err_calls as f64;
+        }
+        0.0
+    }
+
+    pub fn get_err_ok_ratio(&self) -> Option<f64> {
+        let ok_percall_time = self.get_ok_percall_time();
+        if self.err_calls == 0 || ok_percall_time <= 0.0 {
+            return None;
+        }
+        Some(self.get_err_percall_time() / ok_percall_time)
+    }
 }
 
 impl PartialEq for CallableMetrics {
@@ -119,6 +511,10 @@ pub struct ProfileMetricsReport {
     header_color: ANSICode,
     call_color: ANSICode,
     total_time_color: ANSICode,
+    time_format: TimeFormat,
+    theme: ReportTheme,
+    overhead_ns: Option<f64>,
+    subtract_overhead: bool,
 }
 
 impl ProfileMetricsReport {
@@ -133,9 +529,51 @@ impl ProfileMetricsReport {
             header_color,
             call_color,
             total_time_color,
+            time_format: TimeFormat::new(),
+            theme: ReportTheme::unicode(),
+            overhead_ns: None,
+            subtract_overhead: false,
         }
     }
 
+    pub fn set_overhead(&mut self, overhead_ns: f64, subtract_overhead: bool) -> &mut Self {
+        self.overhead_ns = Some(overhead_ns);
+        self.subtract_overhead = subtract_overhead;
+        self
+    }
+
+    pub fn set_time_format(&mut self, time_format: TimeFormat) -> &mut Self {
+        self.time_format = time_format;
+        self
+    }
+
+    pub fn set_theme(&mut self, theme: ReportTheme) -> &mut Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn set_terminal(&mut self, terminal: Terminal) -> &mut Self {
+        self.terminal = terminal;
+        self
+    }
+
+    pub fn take_output(&mut self) -> String {
+        self.terminal.take_output()
+    }
+
+    pub(crate) fn get_theme(&self) -> &ReportTheme {
+        &self.theme
+    }
+
+    pub(crate) fn format_time(&self, nanos: f64) -> String {
+        let mut time_format: TimeFormat = self.time_format;
+        if self.theme.ascii_units {
+            time_format.set_ascii(true);
+        }
+        let formatter = TimeFormatterNs::new(nanos).with_format(time_format);
+        self.theme.format_decimal(&formatter.auto_format())
+    }
+
     fn get_header_color(realtime: bool) -> ANSICode {
         if realtime {
             return ANSICode::Yellow;
@@ -165,27 +603,116 @@ impl ProfileMetricsReport {
         percentage
     }
 
-    fn write_primary_call_header(&mut self, call_metrics: &CallableMetrics) {
-        let pcall_name = &call_metrics.name;
-        let profile_header = format!("█ PROFILE: {} █", pcall_name);
-        let separator = "=".repeat(profile_header.len());
-        let string = format!("\n{}\n{}", profile_header, separator);
+    fn format_outcomes(&self, call_metrics: &CallableMetrics) -> String {
+        if call_metrics.ok_calls == 0 && call_metrics.err_calls == 0 {
+            return String::new();
+        }
+        let (ls, fs) = (&self.theme.line_separator, &self.theme.field_separator);
+        let ok_calls = self.theme.format_count(call_metrics.ok_calls);
+        let err_calls = self.theme.format_count(call_metrics.err_calls);
+        let ok_percall = self.format_time(call_metrics.get_ok_percall_time());
+        let err_percall = self.format_time(call_metrics.get_err_percall_time());
+
+        let mut string = format!(
+            "{}Ok: [{}]{}PerCall: [{}]{}Err: [{}]{}PerCall: [{}]",
+            ls, ok_calls, fs, ok_percall, ls, err_calls, fs, err_percall
+        );
+        if let Some(ratio) = call_metrics.get_err_ok_ratio() {
+            let times = if self.theme.ascii_units { "x" } else { "×" };
+            let ratio = self.theme.format_decimal(&format!("{:.2}", ratio));
+            string += &format!("{}Err/Ok: [{}{}]", fs, ratio, times);
+        }
+        string
+    }
+
+    fn format_sampling(&self, call_metrics: &CallableMetrics) -> String {
+        if !call_metrics.is_sampled() {
+            return String::new();
+        }
+        let timed_calls = self.theme.format_count(call_metrics.timed_calls);
+        let fs = &self.theme.field_separator;
+        format!("{}Sampled: [{}] (extrapolated)", fs, timed_calls)
+    }
+
+    fn format_windows(&self, call_metrics: &CallableMetrics) -> String {
+        let (ls, fs) = (&self.theme.line_separator, &self.theme.field_separator);
+        let mut string = String::new();
+        for window in call_metrics.windows.iter() {
+            let rate = self
+                .theme
+                .format_decimal(&format!("{:.1}", window.calls_per_sec));
+            string += &format!(
+                "{}Last {}s: [{}/s]{}Mean: [{}]{}P50: [{}]{}P90: [{}]{}P99: [{}]",
+                ls,
+                window.window.as_secs(),
+                rate,
+                fs,
+                self.format_time(window.mean_ns),
+                fs,
+                self.format_time(window.p50_ns),
+                fs,
+                self.format_time(window.p90_ns),
+                fs,
+                self.format_time(window.p99_ns)
+            );
+        }
+        string
+    }
+
+    fn format_panics(&self, call_metrics: &CallableMetrics) -> String {
+        if call_metrics.npanics == 0 {
+            return String::new();
+        }
+        let npanics = self.theme.format_count(call_metrics.npanics);
+        format!("{}Panics: [{}]", self.theme.field_separator, npanics)
+    }
+
+    pub(crate) fn write_header(&mut self, title: &str) {
+        let header = self.theme.format_header(title);
         self.terminal.set_ansi_color(self.header_color);
+        self.terminal.write(&format!("\n{}", header));
+    }
+
+    // One entry per call, followed by the call rule and written in the call
+    // color unless another is given.
+    pub(crate) fn write_entry(&mut self, body: String, color: Option<ANSICode>) {
+        let string = self.theme.append_rule(body, &self.theme.call_rule);
+        self.terminal
+            .set_ansi_color(color.unwrap_or(self.call_color));
+        self.terminal.write(&string);
+    }
+
+    pub(crate) fn write_total(&mut self, body: &str) {
+        let string = self.theme.format_total(body);
+        self.terminal.set_ansi_color(self.total_time_color);
         self.terminal.write(&string);
     }
 
+    fn write_primary_call_header(&mut self, call_metrics: &CallableMetrics) {
+        self.write_header(&format!("PROFILE: {}", call_metrics.name));
+    }
+
     fn write_primacy_call_report(&mut self, pcall_metrics: &CallableMetrics) {
         let pcall_time_ns = pcall_metrics.time_ns;
-        let pcall_ncalls = pcall_metrics.ncalls;
+        let pcall_ncalls = self.theme.format_count(pcall_metrics.ncalls);
         let percall_time_ns = pcall_metrics.get_percall_time();
 
-        let pcall_time = TimeFormatterNs::new(pcall_time_ns).auto_format();
-        let percall_time = TimeFormatterNs::new(percall_time_ns).auto_format();
+        let pcall_time = self.format_time(pcall_time_ns);
+        let percall_time = self.format_time(percall_time_ns);
 
-        let string = format!(
-            "Profile Time: [{}]\nNCalls: [{}] — PerCall: [{}]\n——————\n",
-            pcall_time, pcall_ncalls, percall_time
+        let (ls, fs) = (&self.theme.line_separator, &self.theme.field_separator);
+        let mut string = format!(
+            "Profile Time: [{}]{}NCalls: [{}]{}PerCall: [{}]",
+            pcall_time, ls, pcall_ncalls, fs, percall_time
         );
+        string += &self.format_sampling(pcall_metrics);
+        string += &self.format_panics(pcall_metrics);
+        string += &self.format_outcomes(pcall_metrics);
+        string += &self.format_windows(pcall_metrics);
+        let mut string = self.theme.append_rule(string, &self.theme.primary_rule);
+        if !self.theme.primary_rule.is_empty() {
+            string.push('\n');
+        }
         self.terminal.set_ansi_color(self.call_color);
         self.terminal.write(&string);
     }
@@ -193,25 +720,30 @@ impl ProfileMetricsReport {
     fn write_call_report(&mut self, call_metrics: &CallableMetrics, pcall_time: f64) {
         let call_name = &call_metrics.name;
         let call_time_ns = call_metrics.time_ns;
-        let call_ncalls = call_metrics.ncalls;
+        let call_ncalls = self.theme.format_count(call_metrics.ncalls);
         let percall_time_ns = call_metrics.get_percall_time();
 
         let prc = self.get_relative_percentage(pcall_time, call_time_ns);
-        let call_time = TimeFormatterNs::new(call_time_ns).auto_format();
-        let percall_time = TimeFormatterNs::new(percall_time_ns).auto_format();
-
-        let string = format!(
-            "Name: {}\nTime: [{}] — T%: {:.2}%\nNCalls: [{}] — PerCall: [{}]\n——",
-            call_name, call_time, prc, call_ncalls, percall_time
+        let prc = self.theme.format_decimal(&format!("{:.2}", prc));
+        let call_time = self.format_time(call_time_ns);
+        let percall_time = self.format_time(percall_time_ns);
+
+        let (ls, fs) = (&self.theme.line_separator, &self.theme.field_separator);
+        let mut string = format!(
+            "Name: {}{}Time: [{}]{}T%: {}%{}NCalls: [{}]{}PerCall: [{}]",
+            call_name, ls, call_time, fs, prc, ls, call_ncalls, fs, percall_time
         );
-        self.terminal.set_ansi_color(self.call_color);
-        self.terminal.write(&string);
+        string += &self.format_sampling(call_metrics);
+        string += &self.format_panics(call_metrics);
+        string += &self.format_outcomes(call_metrics);
+        string += &self.format_windows(call_metrics);
+        self.write_entry(string, None);
     }
 
     fn get_total_time(
         &self,
-        callable_refs: &MutexGuard<HashMap<u64, CallableMetrics>>,
-        timing_refs: &MutexGuard<HashMap<u64, HashMap<u64, CallableMetrics>>>,
+        callable_refs: &HashMap<u64, CallableMetrics>,
+        timing_refs: &HashMap<u64, HashMap<u64, CallableMetrics>>,
     ) -> f64 {
         let mut total_time = 0.0;
         for (pcall_hash, _) in timing_refs.iter() {
@@ -222,8 +754,8 @@ impl ProfileMetricsReport {
 
     pub fn write_report(
         &mut self,
-        callable_refs: &MutexGuard<HashMap<u64, CallableMetrics>>,
-        timing_refs: &MutexGuard<HashMap<u64, HashMap<u64, CallableMetrics>>>,
+        callable_refs: &HashMap<u64, CallableMetrics>,
+        timing_refs: &HashMap<u64, HashMap<u64, CallableMetrics>>,
     ) {
         for (pcall_hash, subcalls) in timing_refs.iter() {
             let pcall_metrics = callable_refs.get(pcall_hash).unwrap();
@@ -231,7 +763,7 @@ impl ProfileMetricsReport {
             let pcall_time = pcall_metrics.time_ns;
 
             for (_, subcall_metrics) in subcalls.iter() {
-                if subcall_metrics == pcall_metrics {
+                if subcall_metrics.call_hash == pcall_metrics.call_hash {
                     continue;
                 }
                 self.write_call_report(subcall_metrics, pcall_time);
@@ -239,10 +771,17 @@ impl ProfileMetricsReport {
             self.write_primacy_call_report(pcall_metrics);
         }
         let total_time_ns = self.get_total_time(callable_refs, timing_refs);
-        let total_time = TimeFormatterNs::new(total_time_ns).auto_format();
-
-        let string = format!("――― Total Time: [{}] ―――\n\n\n", total_time);
-        self.terminal.set_ansi_color(self.total_time_color);
-        self.terminal.write(&string);
+        let total_time = self.format_time(total_time_ns);
+
+        let mut total = format!("Total Time: [{}]", total_time);
+        if let Some(overhead_ns) = self.overhead_ns {
+            let overhead = self.format_time(overhead_ns);
+            let fs = &self.theme.field_separator;
+            total += &format!("{}Overhead: [{}/call]", fs, overhead);
+            if self.subtract_overhead {
+                total += " (subtracted)";
+            }
+        }
+        self.write_total(&total);
     }
 }
@@ -1,4 +1,20 @@
+pub mod baseline;
+pub mod benchmark;
+pub mod budget;
+pub mod clock;
+pub mod comparison;
+#[cfg(feature = "tracing")]
+pub mod layer;
 pub mod metrics;
+pub mod parser;
 pub mod profiler;
+mod recorder;
+pub mod shared;
 pub mod terminal;
+pub mod theme;
+pub mod ticker;
 pub mod timer;
+pub mod window;
+
+#[cfg(feature = "macros")]
+pub use timer_module_macros::profile;
@@ -0,0 +1,314 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result};
+use std::time::Duration;
+
+use super::metrics::TimeUnit;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    Empty,
+    Negative,
+    ExpectedNumber,
+    ExpectedUnit,
+    UnknownUnit(String),
+    UnitOrder(String),
+    UnexpectedCharacter(char),
+    InvalidClock,
+    Overflow,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseDurationError {
+    pub kind: ParseErrorKind,
+    pub position: usize,
+    pub input: String,
+}
+
+impl Display for ParseDurationError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let message: String = match &self.kind {
+            ParseErrorKind::Empty => "empty duration".to_string(),
+            ParseErrorKind::Negative => "durations cannot be negative".to_string(),
+            ParseErrorKind::ExpectedNumber => "expected a number".to_string(),
+            ParseErrorKind::ExpectedUnit => "expected a unit".to_string(),
+            ParseErrorKind::UnknownUnit(unit) => format!("unknown unit '{}'", unit),
+            ParseErrorKind::UnitOrder(unit) => {
+                format!("unit '{}' is repeated or out of order", unit)
+            }
+            ParseErrorKind::UnexpectedCharacter(c) => format!("unexpected character '{}'", c),
+            ParseErrorKind::InvalidClock => "invalid clock field".to_string(),
+            ParseErrorKind::Overflow => "duration is too large".to_string(),
+        };
+        write!(
+            f,
+            "{} at position {} in \"{}\"",
+            message, self.position, self.input
+        )
+    }
+}
+
+impl Error for ParseDurationError {}
+
+/// Parses every form `TimeFormatterNs` can emit: single units (`2.5ms`,
+/// `1.03h`), compound (`1h 02m 05.3s`), clock (`01:02:05.300`) and
+/// ISO-8601 (`PT1H2M5.3S`).
+pub fn parse_duration(input: &str) -> std::result::Result<Duration, ParseDurationError> {
+    let mut parser = DurationParser::new(input);
+    let nanos: u128 = parser.parse()?;
+    parser.to_duration(nanos)
+}
+
+struct DurationParser<'a> {
+    input: &'a str,
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl<'a> DurationParser<'a> {
+    fn new(input: &'a str) -> Self {
+        DurationParser {
+            input,
+            chars: input.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn parse(&mut self) -> std::result::Result<u128, ParseDurationError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => return Err(self.error(ParseErrorKind::Empty)),
+            Some('-') => return Err(self.error(ParseErrorKind::Negative)),
+            Some('+') => self.position += 1,
+            _ => {}
+        }
+
+        let nanos: u128 = if self.peek() == Some('P') {
+            self.parse_iso8601()?
+        } else if self.chars.contains(&':') {
+            self.parse_clock()?
+        } else {
+            self.parse_compound()?
+        };
+
+        self.skip_whitespace();
+        if let Some(c) = self.peek() {
+            return Err(self.error(ParseErrorKind::UnexpectedCharacter(c)));
+        }
+        Ok(nanos)
+    }
+
+    fn parse_compound(&mut self) -> std::result::Result<u128, ParseDurationError> {
+        let mut nanos: u128 = 0;
+        let mut previous_unit: Option<TimeUnit> = None;
+
+        while self.peek().is_some() {
+            let (integer, fraction) = self.parse_number()?;
+            self.skip_whitespace();
+
+            let unit_position: usize = self.position;
+            let symbol: String = self.take_while(|c| c.is_alphabetic());
+            if symbol.is_empty() {
+                return Err(self.error(ParseErrorKind::ExpectedUnit));
+            }
+            let unit: TimeUnit = match unit_from_symbol(&symbol) {
+                Some(unit) => unit,
+                None => {
+                    self.position = unit_position;
+                    return Err(self.error(ParseErrorKind::UnknownUnit(symbol)));
+                }
+            };
+            if let Some(previous_unit) = previous_unit {
+                if unit_nanos(unit) >= unit_nanos(previous_unit) {
+                    self.position = unit_position;
+                    return Err(self.error(ParseErrorKind::UnitOrder(symbol)));
+                }
+            }
+            previous_unit = Some(unit);
+
+            let value: u128 = self.scale(&integer, &fraction, unit_nanos(unit))?;
+            nanos = self.checked_add(nanos, value)?;
+            self.skip_whitespace();
+        }
+        Ok(nanos)
+    }
+
+    fn parse_clock(&mut self) -> std::result::Result<u128, ParseDurationError> {
+        let mut fields: Vec<(String, String, usize)> = Vec::new();
+        loop {
+            let field_position: usize = self.position;
+            let (integer, fraction) = self.parse_number()?;
+            fields.push((integer, fraction, field_position));
+            if self.peek() != Some(':') {
+                break;
+            }
+            self.position += 1;
+        }
+
+        if fields.len() > 3 {
+            self.position = fields[3].2;
+            return Err(self.error(ParseErrorKind::InvalidClock));
+        }
+
+        let units: [TimeUnit; 3] = [TimeUnit::Hours, TimeUnit::Minutes, TimeUnit::Seconds];
+        let units: &[TimeUnit] = &units[3 - fields.len()..];
+        let mut nanos: u128 = 0;
+        for (index, ((integer, fraction, field_position), unit)) in
+            fields.iter().zip(units.iter()).enumerate()
+        {
+            let is_last: bool = index == fields.len() - 1;
+            let is_leading: bool = index == 0;
+            let out_of_range: bool = !is_leading && integer.parse::<u128>().unwrap_or(60) >= 60;
+            if (!is_last && !fraction.is_empty()) || out_of_range {
+                self.position = *field_position;
+                return Err(self.error(ParseErrorKind::InvalidClock));
+            }
+            let value: u128 = self.scale(integer, fraction, unit_nanos(*unit))?;
+            nanos = self.checked_add(nanos, value)?;
+        }
+        Ok(nanos)
+    }
+
+    fn parse_iso8601(&mut self) -> std::result::Result<u128, ParseDurationError> {
+        self.position += 1;
+        let mut nanos: u128 = 0;
+        let mut in_time: bool = false;
+        let mut previous_unit: Option<TimeUnit> = None;
+
+        while let Some(c) = self.peek() {
+            if c == 'T' && !in_time {
+                in_time = true;
+                self.position += 1;
+                continue;
+            }
+            let (integer, fraction) = self.parse_number()?;
+            let unit_position: usize = self.position;
+            let symbol: char = match self.peek() {
+                Some(symbol) => symbol,
+                None => return Err(self.error(ParseErrorKind::ExpectedUnit)),
+            };
+            let unit: TimeUnit = match (in_time, symbol) {
+                (false, 'D') => TimeUnit::Days,
+                (true, 'H') => TimeUnit::Hours,
+                (true, 'M') => TimeUnit::Minutes,
+                (true, 'S') => TimeUnit::Seconds,
+                _ => return Err(self.error(ParseErrorKind::UnknownUnit(symbol.to_string()))),
+            };
+            if let Some(previous_unit) = previous_unit {
+                if unit_nanos(unit) >= unit_nanos(previous_unit) {
+                    self.position = unit_position;
+                    return Err(self.error(ParseErrorKind::UnitOrder(symbol.to_string())));
+                }
+            }
+            previous_unit = Some(unit);
+            self.position += 1;
+
+            let value: u128 = self.scale(&integer, &fraction, unit_nanos(unit))?;
+            nanos = self.checked_add(nanos, value)?;
+        }
+
+        if previous_unit.is_none() {
+            return Err(self.error(ParseErrorKind::ExpectedNumber));
+        }
+        Ok(nanos)
+    }
+
+    fn parse_number(&mut self) -> std::result::Result<(String, String), ParseDurationError> {
+        let integer: String = self.take_while(|c| c.is_ascii_digit());
+        let mut fraction: String = String::new();
+        if self.peek() == Some('.') {
+            self.position += 1;
+            fraction = self.take_while(|c| c.is_ascii_digit());
+        }
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(self.error(ParseErrorKind::ExpectedNumber));
+        }
+        Ok((integer, fraction))
+    }
+
+    fn scale(
+        &self,
+        integer: &str,
+        fraction: &str,
+        unit_nanos: u128,
+    ) -> std::result::Result<u128, ParseDurationError> {
+        let overflow = || self.error(ParseErrorKind::Overflow);
+        let integer: u128 = match integer {
+            "" => 0,
+            _ => integer.parse::<u128>().map_err(|_| overflow())?,
+        };
+        let mut nanos: u128 = integer.checked_mul(unit_nanos).ok_or_else(overflow)?;
+
+        // Horner's rule from the last digit scales the fraction exactly and
+        // stays below ten units however many digits there are. The digit each
+        // step divides off leads the fraction of a nanosecond that is left,
+        // so the last one decides the rounding.
+        let mut fraction_nanos: u128 = 0;
+        let mut round_up: bool = false;
+        for digit in fraction.chars().rev() {
+            let scaled: u128 = digit.to_digit(10).unwrap() as u128 * unit_nanos + fraction_nanos;
+            fraction_nanos = scaled / 10;
+            round_up = scaled % 10 >= 5;
+        }
+        if round_up {
+            fraction_nanos += 1;
+        }
+        nanos = nanos.checked_add(fraction_nanos).ok_or_else(overflow)?;
+        Ok(nanos)
+    }
+
+    fn to_duration(&self, nanos: u128) -> std::result::Result<Duration, ParseDurationError> {
+        let secs: u64 = u64::try_from(nanos / 1_000_000_000)
+            .map_err(|_| self.error(ParseErrorKind::Overflow))?;
+        let subsec_nanos: u32 = (nanos % 1_000_000_000) as u32;
+        Ok(Duration::new(secs, subsec_nanos))
+    }
+
+    fn checked_add(&self, a: u128, b: u128) -> std::result::Result<u128, ParseDurationError> {
+        a.checked_add(b)
+            .ok_or_else(|| self.error(ParseErrorKind::Overflow))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn take_while<P>(&mut self, predicate: P) -> String
+    where
+        P: Fn(char) -> bool,
+    {
+        let start: usize = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseDurationError {
+        ParseDurationError {
+            kind,
+            position: self.position,
+            input: self.input.to_string(),
+        }
+    }
+}
+
+fn unit_from_symbol(symbol: &str) -> Option<TimeUnit> {
+    match symbol {
+        "d" => Some(TimeUnit::Days),
+        "h" => Some(TimeUnit::Hours),
+        "m" | "min" => Some(TimeUnit::Minutes),
+        "s" => Some(TimeUnit::Seconds),
+        "ms" => Some(TimeUnit::Milliseconds),
+        "μs" | "µs" | "us" => Some(TimeUnit::Microseconds),
+        "ns" => Some(TimeUnit::Nanoseconds),
+        _ => None,
+    }
+}
+
+fn unit_nanos(unit: TimeUnit) -> u128 {
+    unit.get_nanos() as u128
+}
@@ -1,155 +1,1057 @@
-use std::any::TypeId;
-use std::collections::hash_map::DefaultHasher;
+use std::any::type_name;
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
-use std::sync::{Arc, Mutex, MutexGuard};
-use std::time::{Duration, Instant};
+use std::io;
+use std::panic::{self, AssertUnwindSafe, Location};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
 
+use super::baseline::ProfileBaseline;
+use super::budget::{BudgetReport, PerformanceBudget};
+use super::clock::{Clock, MonotonicClock};
+use super::comparison::BaselineComparison;
+use super::metrics::CallOutcome;
 use super::metrics::CallableMetrics;
 use super::metrics::ProfileMetricsReport;
+use super::metrics::TimeFormat;
+use super::recorder::{self, CallCounters, CallFrame, CallSampler};
+use super::terminal::Terminal;
+use super::theme::ReportTheme;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallIdentity {
+    Merge,
+    Separate,
+}
+
+/// Which calls of a wrapped function are timed. Every call is still counted,
+/// in totals and rolling windows, and the mean time of the sampled calls
+/// stands for the calls skipped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CallSampling {
+    All,
+    EveryNth(u64),
+    Fraction(f64),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CallOverhead {
+    pub timed_ns: f64,
+    pub call_ns: f64,
+}
+
+type SubcallRefs = HashMap<u64, HashMap<u64, Arc<CallCounters>>>;
+type TimingMetrics = HashMap<u64, HashMap<u64, CallableMetrics>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallOrder {
+    TotalTime,
+    PerCallTime,
+    Calls,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProfileTotals {
+    pub callables: usize,
+    pub ncalls: usize,
+    pub npanics: usize,
+    pub time_ns: f64,
+}
+
+/// An immutable copy of a profiler's metrics, covering the profiler clock
+/// interval from `start` (creation or last reset) to `end`.
+#[derive(Clone, Debug)]
+pub struct ProfileSnapshot {
+    callable_metrics: HashMap<u64, CallableMetrics>,
+    timing_metrics: TimingMetrics,
+    start: Duration,
+    end: Duration,
+}
+
+impl ProfileSnapshot {
+    pub fn get_callable_metrics(&self) -> &HashMap<u64, CallableMetrics> {
+        &self.callable_metrics
+    }
+
+    pub fn get_timing_metrics(&self) -> &TimingMetrics {
+        &self.timing_metrics
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// All callables, ordered by module and name.
+    pub fn get_callables(&self) -> Vec<&CallableMetrics> {
+        let mut callables: Vec<&CallableMetrics> = self.callable_metrics.values().collect();
+        callables.sort_by(|a, b| (&a.module, &a.name).cmp(&(&b.module, &b.name)));
+        callables
+    }
+
+    /// Looks a callable up by name or `module::name`.
+    pub fn find_callable(&self, name: &str) -> Option<&CallableMetrics> {
+        self.get_callables()
+            .into_iter()
+            .find(|call_metrics| call_metrics.matches_name(name))
+    }
+
+    /// Calls recorded beneath `name` while it was the outermost profiled
+    /// call, ordered by total time.
+    pub fn get_children(&self, name: &str) -> Vec<&CallableMetrics> {
+        let pcall_hash: u64 = match self.find_callable(name) {
+            Some(call_metrics) => call_metrics.call_hash,
+            None => return Vec::new(),
+        };
+        let mut children: Vec<&CallableMetrics> = self
+            .timing_metrics
+            .get(&pcall_hash)
+            .into_iter()
+            .flat_map(|subcalls| subcalls.values())
+            .filter(|subcall_metrics| subcall_metrics.call_hash != pcall_hash)
+            .collect();
+        children.sort_by(|a, b| b.time_ns.total_cmp(&a.time_ns));
+        children
+    }
+
+    /// Time is the sum of primary calls, the same total the report shows.
+    pub fn get_totals(&self) -> ProfileTotals {
+        let mut totals = ProfileTotals {
+            callables: self.callable_metrics.len(),
+            ..ProfileTotals::default()
+        };
+        for (call_hash, call_metrics) in self.callable_metrics.iter() {
+            totals.ncalls += call_metrics.ncalls;
+            totals.npanics += call_metrics.npanics;
+            if self.timing_metrics.contains_key(call_hash) {
+                totals.time_ns += call_metrics.time_ns;
+            }
+        }
+        totals
+    }
+
+    pub fn get_top_callables(&self, count: usize, order: CallOrder) -> Vec<&CallableMetrics> {
+        let mut callables: Vec<&CallableMetrics> = self.get_callables();
+        let key = |call_metrics: &CallableMetrics| match order {
+            CallOrder::TotalTime => call_metrics.time_ns,
+            CallOrder::PerCallTime => call_metrics.get_percall_time(),
+            CallOrder::Calls => call_metrics.ncalls as f64,
+        };
+        callables.sort_by(|a, b| key(b).total_cmp(&key(a)));
+        callables.truncate(count);
+        callables
+    }
+
+    pub fn get_baseline(&self) -> ProfileBaseline {
+        ProfileBaseline::from_metrics(&self.callable_metrics, &self.timing_metrics)
+    }
+
+    /// Metrics recorded between `earlier` and this snapshot of the same
+    /// profiler. Snapshots taken across a `reset` do not diff meaningfully.
+    pub fn diff(&self, earlier: &ProfileSnapshot) -> ProfileSnapshot {
+        let diff_metrics =
+            |later: &HashMap<u64, CallableMetrics>,
+             earlier: Option<&HashMap<u64, CallableMetrics>>| {
+                let mut metrics: HashMap<u64, CallableMetrics> = HashMap::new();
+                for (call_hash, call_metrics) in later.iter() {
+                    let call_metrics = match earlier.and_then(|earlier| earlier.get(call_hash)) {
+                        Some(earlier_metrics) => call_metrics.diff(earlier_metrics),
+                        None => call_metrics.clone(),
+                    };
+                    metrics.insert(*call_hash, call_metrics);
+                }
+                metrics
+            };
+
+        let callable_metrics =
+            diff_metrics(&self.callable_metrics, Some(&earlier.callable_metrics));
+        let mut timing_metrics: TimingMetrics = HashMap::new();
+        for (pcall_hash, subcalls) in self.timing_metrics.iter() {
+            if callable_metrics[pcall_hash].ncalls == 0 {
+                continue;
+            }
+            let earlier_subcalls = earlier.timing_metrics.get(pcall_hash);
+            timing_metrics.insert(*pcall_hash, diff_metrics(subcalls, earlier_subcalls));
+        }
+        ProfileSnapshot {
+            callable_metrics,
+            timing_metrics,
+            start: earlier.end,
+            end: self.end,
+        }
+    }
+}
+
+struct CallableRef {
+    metrics: CallableMetrics,
+    counters: Arc<CallCounters>,
+}
+
+/// A function definition instrumented by `#[profile]`. The call site caches
+/// its registration with the first profiler it reports to; any other profiler
+/// caches the call site's registration itself.
+pub struct CallSite {
+    name: &'static str,
+    module: &'static str,
+    path: Option<fn() -> &'static str>,
+    call_ref: OnceLock<CallSiteRef>,
+}
+
+impl CallSite {
+    pub const fn new(name: &'static str, module: &'static str) -> CallSite {
+        CallSite {
+            name,
+            module,
+            path: None,
+            call_ref: OnceLock::new(),
+        }
+    }
+
+    /// A call site named after the function it is in. `path` returns the
+    /// type name of an item nested in that function, whose parent is the
+    /// function's full path, e.g. `crate::module::Type::method`. It is
+    /// resolved when the call site is first registered.
+    pub const fn from_path(module: &'static str, path: fn() -> &'static str) -> CallSite {
+        CallSite {
+            name: "",
+            module,
+            path: Some(path),
+            call_ref: OnceLock::new(),
+        }
+    }
+
+    // The function's path below its module, so methods keep their self type.
+    fn get_name(&self) -> &'static str {
+        let Some(path) = self.path else {
+            return self.name;
+        };
+        let path: &'static str = path();
+        let path: &'static str = path.rsplit_once("::").map_or(path, |(parent, _)| parent);
+        path.strip_prefix(self.module)
+            .and_then(|name| name.strip_prefix("::"))
+            .unwrap_or(path)
+    }
+}
+
+struct CallSiteRef {
+    profiler_id: usize,
+    call_hash: u64,
+    counters: Arc<CallCounters>,
+    sampler: CallSampler,
+}
+
+static GLOBAL_PROFILER: OnceLock<TimeProfiler> = OnceLock::new();
+
+// With the `disable-profiling` feature wrappers and `#[profile]` call their
+// function directly, and nothing is registered with the profiler.
+pub(crate) const PROFILING_ENABLED: bool = !cfg!(feature = "disable-profiling");
+
+const CALIBRATION_ROUNDS: usize = 5;
+const CALIBRATION_CALLS: usize = 1_000;
 
 pub struct TimeProfiler {
+    profiler_id: usize,
     realtime: bool,
-    callable_refs: Arc<Mutex<HashMap<u64, CallableMetrics>>>,
-    timing_refs: Arc<Mutex<HashMap<u64, HashMap<u64, CallableMetrics>>>>,
-    pcall_hash: Option<u64>,
+    call_identity: CallIdentity,
+    call_sampling: CallSampling,
+    rolling_windows: bool,
+    callable_refs: Arc<Mutex<HashMap<u64, CallableRef>>>,
+    timing_refs: Arc<Mutex<SubcallRefs>>,
+    merged_timing: Arc<Mutex<TimingMetrics>>,
+    reset_time: Arc<Mutex<Duration>>,
+    call_keys: Arc<Mutex<HashMap<String, u64>>>,
+    call_site_refs: Arc<Mutex<HashMap<usize, Arc<CallSiteRef>>>>,
+    clock: Arc<dyn Clock>,
+    overhead: Option<CallOverhead>,
+    subtract_overhead: bool,
+    time_format: TimeFormat,
+    report_theme: ReportTheme,
 }
 
 impl TimeProfiler {
     pub fn new(realtime: bool) -> Self {
         TimeProfiler {
+            profiler_id: recorder::next_profiler_id(),
             realtime,
+            call_identity: CallIdentity::Merge,
+            call_sampling: CallSampling::All,
+            rolling_windows: false,
             callable_refs: Arc::new(Mutex::new(HashMap::new())),
             timing_refs: Arc::new(Mutex::new(HashMap::new())),
-            pcall_hash: None,
+            merged_timing: Arc::new(Mutex::new(HashMap::new())),
+            reset_time: Arc::new(Mutex::new(Duration::ZERO)),
+            call_keys: Arc::new(Mutex::new(HashMap::new())),
+            call_site_refs: Arc::new(Mutex::new(HashMap::new())),
+            clock: Arc::new(MonotonicClock::new()),
+            overhead: None,
+            subtract_overhead: false,
+            time_format: TimeFormat::new(),
+            report_theme: ReportTheme::unicode(),
         }
     }
 
+    /// The process-wide profiler `#[profile]` reports to by default.
+    pub fn global() -> &'static TimeProfiler {
+        GLOBAL_PROFILER.get_or_init(|| TimeProfiler::new(false))
+    }
+
+    /// Installs a configured profiler as the global one. Fails, handing the
+    /// profiler back, once the global profiler has been installed or used.
+    pub fn set_global(profiler: TimeProfiler) -> Result<(), Box<TimeProfiler>> {
+        GLOBAL_PROFILER.set(profiler).map_err(Box::new)
+    }
+
+    pub fn set_call_identity(&mut self, call_identity: CallIdentity) -> &mut Self {
+        self.call_identity = call_identity;
+        self
+    }
+
+    pub fn set_call_sampling(&mut self, call_sampling: CallSampling) -> &mut Self {
+        self.call_sampling = call_sampling;
+        self
+    }
+
+    /// Keeps 1s, 10s and 60s rolling statistics for wrappers created
+    /// afterwards, shown in every report including realtime ones.
+    pub fn set_rolling_windows(&mut self, rolling_windows: bool) -> &mut Self {
+        self.rolling_windows = rolling_windows;
+        self
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+        *self.reset_time.lock().unwrap() = self.clock.now();
+        self
+    }
+
+    /// Subtracts the measurement overhead from every recorded call,
+    /// calibrating it first unless it was calibrated or set already.
+    pub fn set_subtract_overhead(&mut self, subtract_overhead: bool) -> &mut Self {
+        if subtract_overhead && self.overhead.is_none() {
+            self.calibrate();
+        }
+        self.subtract_overhead = subtract_overhead;
+        self
+    }
+
+    pub fn set_overhead(&mut self, overhead: CallOverhead) -> &mut Self {
+        self.overhead = Some(overhead);
+        self
+    }
+
+    pub fn get_overhead(&self) -> Option<CallOverhead> {
+        self.overhead
+    }
+
+    // Profiles an empty function with this profiler's clock. `timed_ns` is
+    // the bookkeeping that lands inside a call's own measurement, `call_ns`
+    // is the full cost a wrapped call adds to the call enclosing it. The
+    // fastest round is kept since noise only ever adds time. A clock that
+    // does not follow real time has no overhead to measure.
+    pub fn calibrate(&mut self) -> &mut Self {
+        if !PROFILING_ENABLED || !self.clock.is_real_time() {
+            return self;
+        }
+        let mut overhead: Option<CallOverhead> = None;
+        for _ in 0..CALIBRATION_ROUNDS {
+            let mut profiler: TimeProfiler = TimeProfiler::new(false);
+            profiler.clock = self.clock.clone();
+            let wrapped = profiler.function_wrapper(|_: ()| {});
+
+            let start_time: Duration = self.clock.now();
+            for _ in 0..CALIBRATION_CALLS {
+                wrapped(());
+            }
+            let elapsed_time: Duration = self.clock.now().saturating_sub(start_time);
+            drop(wrapped);
+
+            let call_metrics = profiler.get_callable_metrics();
+            let timed_ns: f64 = call_metrics.values().next().unwrap().get_percall_time();
+            let call_ns: f64 = elapsed_time.as_nanos() as f64 / CALIBRATION_CALLS as f64;
+
+            overhead = match overhead {
+                Some(overhead) if overhead.call_ns <= call_ns => Some(overhead),
+                _ => Some(CallOverhead { timed_ns, call_ns }),
+            };
+        }
+        self.overhead = overhead;
+        self
+    }
+
+    pub fn set_time_format(&mut self, time_format: TimeFormat) -> &mut Self {
+        self.time_format = time_format;
+        self
+    }
+
+    pub fn set_report_theme(&mut self, report_theme: ReportTheme) -> &mut Self {
+        self.report_theme = report_theme;
+        self
+    }
+
     pub fn print_report(&self) {
+        let (callable_metrics, timing_metrics) = self.collect_metrics();
+        self.write_report(&callable_metrics, &timing_metrics, Terminal::new());
+    }
+
+    /// The report `print_report` prints, without colors.
+    pub fn get_report_string(&self) -> String {
+        let (callable_metrics, timing_metrics) = self.collect_metrics();
+        self.write_report(&callable_metrics, &timing_metrics, Terminal::buffered())
+    }
+
+    pub fn print_snapshot(&self, snapshot: &ProfileSnapshot) {
+        let terminal: Terminal = Terminal::new();
+        self.write_report(
+            &snapshot.callable_metrics,
+            &snapshot.timing_metrics,
+            terminal,
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn emit_report(&self) {
+        self.emit_snapshot(&self.snapshot());
+    }
+
+    /// Emits one `tracing` event per callable, followed by one per subcall
+    /// of it with the primary call it ran beneath as `parent`.
+    #[cfg(feature = "tracing")]
+    pub fn emit_snapshot(&self, snapshot: &ProfileSnapshot) {
+        for call_metrics in snapshot.get_callables() {
+            tracing::info!(
+                name = %call_metrics.name,
+                module = %call_metrics.module,
+                ncalls = call_metrics.ncalls,
+                total_ns = call_metrics.time_ns,
+                percall_ns = call_metrics.get_percall_time(),
+                "profiled {}",
+                call_metrics.name
+            );
+            let mut subcalls: Vec<&CallableMetrics> = snapshot
+                .timing_metrics
+                .get(&call_metrics.call_hash)
+                .into_iter()
+                .flat_map(|subcalls| subcalls.values())
+                .collect();
+            subcalls.sort_by(|a, b| a.name.cmp(&b.name));
+            for subcall_metrics in subcalls {
+                tracing::info!(
+                    name = %subcall_metrics.name,
+                    module = %subcall_metrics.module,
+                    parent = %call_metrics.name,
+                    ncalls = subcall_metrics.ncalls,
+                    total_ns = subcall_metrics.time_ns,
+                    percall_ns = subcall_metrics.get_percall_time(),
+                    "profiled {} in {}",
+                    subcall_metrics.name,
+                    call_metrics.name
+                );
+            }
+        }
+    }
+
+    fn write_report(
+        &self,
+        callable_metrics: &HashMap<u64, CallableMetrics>,
+        timing_metrics: &TimingMetrics,
+        terminal: Terminal,
+    ) -> String {
         let mut metrics_report: ProfileMetricsReport = ProfileMetricsReport::new(self.realtime);
-        let callable_refs: MutexGuard<HashMap<u64, CallableMetrics>> =
+        metrics_report.set_time_format(self.time_format);
+        metrics_report.set_theme(self.report_theme.clone());
+        metrics_report.set_terminal(terminal);
+        if let Some(overhead) = self.overhead {
+            metrics_report.set_overhead(overhead.timed_ns, self.subtract_overhead);
+        }
+        metrics_report.write_report(callable_metrics, timing_metrics);
+        metrics_report.take_output()
+    }
+
+    pub fn snapshot(&self) -> ProfileSnapshot {
+        let start: Duration = *self.reset_time.lock().unwrap();
+        let (callable_metrics, timing_metrics) = self.collect_metrics();
+        ProfileSnapshot {
+            callable_metrics,
+            timing_metrics,
+            start,
+            end: self.clock.now(),
+        }
+    }
+
+    /// Clears all recorded metrics. Existing wrappers stay registered and
+    /// keep recording into the cleared totals.
+    pub fn reset(&self) {
+        let mut callable_refs: MutexGuard<HashMap<u64, CallableRef>> =
             self.callable_refs.lock().unwrap();
-        let timing_refs: MutexGuard<HashMap<u64, HashMap<u64, CallableMetrics>>> =
-            self.timing_refs.lock().unwrap();
+        let timing_refs: MutexGuard<SubcallRefs> = self.timing_refs.lock().unwrap();
+        for callable_ref in callable_refs.values_mut() {
+            callable_ref.metrics = callable_ref.metrics.clone_and_reset();
+            callable_ref.counters.reset();
+        }
+        for counters in timing_refs.values().flat_map(|subcalls| subcalls.values()) {
+            counters.reset();
+        }
+        self.merged_timing.lock().unwrap().clear();
+        *self.reset_time.lock().unwrap() = self.clock.now();
+    }
+
+    pub fn get_baseline(&self) -> ProfileBaseline {
+        let (callable_metrics, timing_metrics) = self.collect_metrics();
+        ProfileBaseline::from_metrics(&callable_metrics, &timing_metrics)
+    }
+
+    pub fn save_baseline<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.get_baseline().save(path)
+    }
+
+    pub fn compare_baseline(
+        &self,
+        baseline: &ProfileBaseline,
+        noise_threshold_prc: f64,
+    ) -> BaselineComparison {
+        baseline.compare(&self.get_callable_metrics(), noise_threshold_prc)
+    }
+
+    pub fn print_comparison(&self, comparison: &BaselineComparison) {
+        let mut metrics_report: ProfileMetricsReport = ProfileMetricsReport::new(self.realtime);
+        metrics_report.set_time_format(self.time_format);
+        metrics_report.set_theme(self.report_theme.clone());
+        comparison.write_report(&mut metrics_report);
+    }
+
+    pub fn merge_profiler(&self, other: &TimeProfiler) {
+        self.merge_profile(&other.get_baseline());
+    }
+
+    /// Adds a saved or collected profile to this profiler's metrics, so one
+    /// report covers several processes or test shards. Callables are matched
+    /// by module and name.
+    pub fn merge_profile(&self, profile: &ProfileBaseline) {
+        let mut call_hashes: HashMap<u64, u64> = HashMap::new();
+        for call_metrics in profile.calls.iter() {
+            let (call_hash, counters) = self.get_merge_ref(call_metrics);
+            let mut callable_refs: MutexGuard<HashMap<u64, CallableRef>> =
+                self.callable_refs.lock().unwrap();
+            callable_refs
+                .get_mut(&call_hash)
+                .unwrap()
+                .metrics
+                .merge(call_metrics);
+            if profile.is_primary(call_metrics.call_hash) {
+                counters.mark_primary();
+            }
+            call_hashes.insert(call_metrics.call_hash, call_hash);
+        }
+
+        let mut merged_timing: MutexGuard<TimingMetrics> = self.merged_timing.lock().unwrap();
+        for (pcall_hash, subcalls) in profile.subcalls.iter() {
+            let pcall_hash: u64 = match call_hashes.get(pcall_hash) {
+                Some(pcall_hash) => *pcall_hash,
+                None => continue,
+            };
+            let merged_subcalls = merged_timing.entry(pcall_hash).or_default();
+            for subcall_metrics in subcalls.iter() {
+                let subcall_hash: u64 = match call_hashes.get(&subcall_metrics.call_hash) {
+                    Some(subcall_hash) => *subcall_hash,
+                    None => continue,
+                };
+                let merged_subcall = merged_subcalls.entry(subcall_hash).or_insert_with(|| {
+                    let mut merged_subcall = subcall_metrics.clone_and_reset();
+                    merged_subcall.call_hash = subcall_hash;
+                    merged_subcall
+                });
+                merged_subcall.merge(subcall_metrics);
+            }
+        }
+    }
+
+    fn get_merge_ref(&self, call_metrics: &CallableMetrics) -> (u64, Arc<CallCounters>) {
+        {
+            let callable_refs: MutexGuard<HashMap<u64, CallableRef>> =
+                self.callable_refs.lock().unwrap();
+            let merge_ref = callable_refs
+                .iter()
+                .find(|(_, callable_ref)| callable_ref.metrics == *call_metrics);
+            if let Some((call_hash, callable_ref)) = merge_ref {
+                return (*call_hash, callable_ref.counters.clone());
+            }
+        }
+        let (module, name) = (&call_metrics.module, &call_metrics.name);
+        let mut identity: String = name.clone();
+        if !module.is_empty() {
+            identity = format!("{}::{}", module, name);
+        }
+        self.add_call_ref(&identity, name, module, false)
+    }
+
+    pub fn get_callables(&self) -> Vec<CallableMetrics> {
+        self.snapshot()
+            .get_callables()
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn find_callable(&self, name: &str) -> Option<CallableMetrics> {
+        self.snapshot().find_callable(name).cloned()
+    }
+
+    pub fn get_children(&self, name: &str) -> Vec<CallableMetrics> {
+        self.snapshot()
+            .get_children(name)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_totals(&self) -> ProfileTotals {
+        self.snapshot().get_totals()
+    }
+
+    pub fn get_top_callables(&self, count: usize, order: CallOrder) -> Vec<CallableMetrics> {
+        let snapshot: ProfileSnapshot = self.snapshot();
+        let top_callables = snapshot.get_top_callables(count, order);
+        top_callables.into_iter().cloned().collect()
+    }
+
+    pub fn check_budget(&self, budget: &PerformanceBudget) -> BudgetReport {
+        budget.check(&self.get_callable_metrics())
+    }
+
+    pub fn get_callable_metrics(&self) -> HashMap<u64, CallableMetrics> {
+        self.collect_metrics().0
+    }
 
-        metrics_report.write_report(&callable_refs, &timing_refs);
-        drop(callable_refs);
-        drop(timing_refs);
+    pub fn get_timing_metrics(&self) -> TimingMetrics {
+        self.collect_metrics().1
     }
 
-    fn append_metrics(&mut self, call_hash: u64, time: Duration) {
-        let mut callable_refs: MutexGuard<HashMap<u64, CallableMetrics>> =
+    fn collect_metrics(&self) -> (HashMap<u64, CallableMetrics>, TimingMetrics) {
+        let callable_refs: MutexGuard<HashMap<u64, CallableRef>> =
             self.callable_refs.lock().unwrap();
-        let mut timing_refs: MutexGuard<HashMap<u64, HashMap<u64, CallableMetrics>>> =
-            self.timing_refs.lock().unwrap();
+        let timing_refs: MutexGuard<SubcallRefs> = self.timing_refs.lock().unwrap();
+        let merged_timing: MutexGuard<TimingMetrics> = self.merged_timing.lock().unwrap();
+
+        let now: Duration = self.clock.now();
+        let mut callable_metrics: HashMap<u64, CallableMetrics> = HashMap::new();
+        let mut timing_metrics: TimingMetrics = HashMap::new();
+        for (call_hash, callable_ref) in callable_refs.iter() {
+            let mut call_metrics: CallableMetrics = callable_ref.metrics.clone();
+            callable_ref.counters.merge_into(&mut call_metrics);
+            call_metrics.windows = callable_ref.counters.get_window_statistics(now);
+            callable_metrics.insert(*call_hash, call_metrics);
+
+            if !callable_ref.counters.is_primary() {
+                continue;
+            }
+            let mut subcalls: HashMap<u64, CallableMetrics> = HashMap::new();
+            for (subcall_hash, counters) in timing_refs.get(call_hash).into_iter().flatten() {
+                let mut subcall_metrics = callable_refs[subcall_hash].metrics.clone_and_reset();
+                counters.merge_into(&mut subcall_metrics);
+                subcalls.insert(*subcall_hash, subcall_metrics);
+            }
+            for (subcall_hash, merged) in merged_timing.get(call_hash).into_iter().flatten() {
+                let subcall_metrics = subcalls
+                    .entry(*subcall_hash)
+                    .or_insert_with(|| merged.clone_and_reset());
+                subcall_metrics.merge(merged);
+            }
+            timing_metrics.insert(*call_hash, subcalls);
+        }
+        (callable_metrics, timing_metrics)
+    }
 
+    fn get_subcall_counters(&self, pcall_hash: u64, call_hash: u64) -> Arc<CallCounters> {
+        let mut timing_refs: MutexGuard<SubcallRefs> = self.timing_refs.lock().unwrap();
+        let pcall_timing = timing_refs.entry(pcall_hash).or_default();
+        pcall_timing.entry(call_hash).or_default().clone()
+    }
+
+    fn get_adjusted_time_ns(&self, time: Duration, call_frame: &CallFrame) -> f64 {
         let time_ns = time.as_nanos() as f64;
-        let pcall_hash = self.pcall_hash;
+        let overhead: CallOverhead = match self.overhead {
+            Some(overhead) if self.subtract_overhead => overhead,
+            _ => return time_ns,
+        };
+        let descendants_ns = call_frame.descendants as f64 * overhead.call_ns;
+        (time_ns - overhead.timed_ns - descendants_ns)
+            .max(0.0)
+            .round()
+    }
 
-        callable_refs.get_mut(&call_hash).unwrap().time_ns += time_ns;
-        callable_refs.get_mut(&call_hash).unwrap().ncalls += 1;
+    // Each completed call is recorded exactly once into its own counters and,
+    // when it ran beneath a primary call on the same thread, exactly once into
+    // that primary call's subcall counters.
+    fn append_metrics(
+        &self,
+        callable_ref: (u64, &CallCounters),
+        pcall_hash: Option<u64>,
+        time: Option<Duration>,
+        outcome: CallOutcome,
+    ) {
+        let call_frame: CallFrame = recorder::pop_frame(self.profiler_id, time.is_some());
+        let time_ns: Option<f64> = time.map(|time| self.get_adjusted_time_ns(time, &call_frame));
+        self.record_sample(callable_ref, pcall_hash, time_ns, outcome);
+    }
 
-        if let Some(pcall_hash) = pcall_hash {
-            if call_hash == pcall_hash {
-                let call_metrics = callable_refs.get_mut(&call_hash).unwrap();
-                call_metrics.time_ns += time_ns;
-                call_metrics.ncalls += 1;
-                self.pcall_hash = None;
+    fn record_sample(
+        &self,
+        callable_ref: (u64, &CallCounters),
+        pcall_hash: Option<u64>,
+        time_ns: Option<f64>,
+        outcome: CallOutcome,
+    ) {
+        let (call_hash, counters) = callable_ref;
+        counters.record(time_ns, outcome);
+        if self.rolling_windows {
+            counters.record_window(self.clock.now(), time_ns);
+        }
 
-                if self.realtime {
-                    self.print_report();
-                }
-            } else {
-                let call_metrics = timing_refs
-                    .get_mut(&pcall_hash)
-                    .unwrap()
-                    .get_mut(&call_hash)
-                    .unwrap();
-
-                call_metrics.time_ns += time_ns;
-                call_metrics.ncalls += 1;
+        match pcall_hash {
+            Some(pcall_hash) => {
+                let key = (self.profiler_id, pcall_hash, call_hash);
+                recorder::record_subcall(key, time_ns, outcome, || {
+                    self.get_subcall_counters(pcall_hash, call_hash)
+                });
             }
+            None if self.realtime => self.print_report(),
+            None => {}
         }
-        drop(callable_refs);
-        drop(timing_refs);
     }
 
-    fn create_callable_metrics<F>(&self, call: &F, call_hash: u64) -> CallableMetrics {
-        let name: &String = &format!("{:p}", &call);
-        let module: &str = "wrapped_function";
+    fn create_callable_metrics(&self, name: &str, module: &str, call_hash: u64) -> CallableMetrics {
+        CallableMetrics::new(String::from(name), String::from(module), call_hash, 0, 0.0)
+    }
+
+    fn add_call_ref(
+        &self,
+        identity: &str,
+        name: &str,
+        module: &str,
+        separate: bool,
+    ) -> (u64, Arc<CallCounters>) {
+        let mut callable_refs: MutexGuard<HashMap<u64, CallableRef>> =
+            self.callable_refs.lock().unwrap();
+        let mut call_keys: MutexGuard<HashMap<String, u64>> = self.call_keys.lock().unwrap();
 
-        let call_metrics = CallableMetrics {
-            name: String::from(name),
-            module: String::from(module),
-            call_hash,
-            ncalls: 0,
-            time_ns: 0.0,
+        let mut identity: String = identity.to_string();
+        let mut name: String = name.to_string();
+        if separate && call_keys.contains_key(&identity) {
+            let mut index: usize = 2;
+            while call_keys.contains_key(&format!("{}#{}", identity, index)) {
+                index += 1;
+            }
+            identity = format!("{}#{}", identity, index);
+            name = format!("{}#{}", name, index);
+        }
+
+        if let Some(call_hash) = call_keys.get(&identity) {
+            let counters = callable_refs[call_hash].counters.clone();
+            return (*call_hash, counters);
+        }
+
+        // Hash collisions between different identities are resolved by
+        // probing, so a call_hash never refers to two callables. Only a
+        // probed hash depends on the order the identities were registered in.
+        let mut call_hash: u64 = hash_identity(&identity);
+        let mut probe: usize = 1;
+        while callable_refs.contains_key(&call_hash) {
+            call_hash = hash_identity(&format!("{}#probe{}", identity, probe));
+            probe += 1;
+        }
+
+        let callable_ref = CallableRef {
+            metrics: self.create_callable_metrics(&name, module, call_hash),
+            counters: Arc::new(CallCounters::default()),
         };
-        call_metrics
+        let counters: Arc<CallCounters> = callable_ref.counters.clone();
+        callable_refs.insert(call_hash, callable_ref);
+        call_keys.insert(identity, call_hash);
+        (call_hash, counters)
     }
 
-    fn hash_type_id(&self, type_id: TypeId) -> u64 {
-        let mut hasher: DefaultHasher = DefaultHasher::new();
-        type_id.hash(&mut hasher);
-        hasher.finish()
+    fn wrap_call<G, F, A, R, C>(
+        &self,
+        register: G,
+        sampling: CallSampling,
+        function: F,
+        classify: C,
+    ) -> impl Fn(A) -> R + '_
+    where
+        G: FnOnce() -> (u64, Arc<CallCounters>),
+        F: Fn(A) -> R + Send + Sync + 'static,
+        A: Send + Sync,
+        R: 'static,
+        C: Fn(&R) -> CallOutcome + Send + Sync + 'static,
+    {
+        let mut callable_ref: (u64, Arc<CallCounters>) = (0, Arc::default());
+        if PROFILING_ENABLED {
+            callable_ref = register();
+        }
+        let (call_hash, counters) = callable_ref;
+        let sampler: CallSampler = CallSampler::new(sampling);
+        if self.rolling_windows {
+            counters.enable_windows();
+        }
+        move |arg: A| {
+            if !PROFILING_ENABLED {
+                return function(arg);
+            }
+            self.record_call(
+                (call_hash, &counters),
+                &sampler,
+                || function(arg),
+                &classify,
+            )
+        }
     }
 
-    fn set_pcall_hash(&mut self, call_hash: u64) {
-        let callable_refs: MutexGuard<HashMap<u64, CallableMetrics>> =
-            self.callable_refs.lock().unwrap();
-        let mut timing_refs: MutexGuard<HashMap<u64, HashMap<u64, CallableMetrics>>> =
-            self.timing_refs.lock().unwrap();
+    fn record_call<F, R, C>(
+        &self,
+        callable_ref: (u64, &CallCounters),
+        sampler: &CallSampler,
+        function: F,
+        classify: C,
+    ) -> R
+    where
+        F: FnOnce() -> R,
+        C: Fn(&R) -> CallOutcome,
+    {
+        let (call_hash, counters) = callable_ref;
+        let pcall_hash: Option<u64> = recorder::push_frame(self.profiler_id, call_hash);
+        if pcall_hash.is_none() {
+            counters.mark_primary();
+        }
+        let start_time: Option<Duration> = sampler.sample().then(|| self.clock.now());
+        let result = panic::catch_unwind(AssertUnwindSafe(function));
+        let time: Option<Duration> = start_time.map(|start| self.clock.now().saturating_sub(start));
+
+        // A panicking call is still recorded and popped off the call
+        // stack before the panic continues to unwind.
+        let outcome: CallOutcome = match &result {
+            Ok(result) => classify(result),
+            Err(_) => CallOutcome::Panicked,
+        };
+        self.append_metrics(callable_ref, pcall_hash, time, outcome);
+        match result {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    pub fn profile_call<F, R>(&self, call_site: &CallSite, function: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        self.profile_call_site(call_site, function, |_: &R| CallOutcome::Returned)
+    }
+
+    pub fn profile_result_call<F, T, E>(&self, call_site: &CallSite, function: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        self.profile_call_site(call_site, function, classify_result)
+    }
+
+    fn profile_call_site<F, R, C>(&self, call_site: &CallSite, function: F, classify: C) -> R
+    where
+        F: FnOnce() -> R,
+        C: Fn(&R) -> CallOutcome,
+    {
+        if !PROFILING_ENABLED {
+            return function();
+        }
+        let call_site_ref = call_site
+            .call_ref
+            .get_or_init(|| self.create_call_site_ref(call_site));
+        if call_site_ref.profiler_id == self.profiler_id {
+            let callable_ref = (call_site_ref.call_hash, call_site_ref.counters.as_ref());
+            return self.record_call(callable_ref, &call_site_ref.sampler, function, classify);
+        }
+
+        // Call sites are statics, so their address identifies them. The
+        // lock is released before the call, which may enter other call sites.
+        let call_site_key: usize = call_site as *const CallSite as usize;
+        let call_site_ref: Arc<CallSiteRef> = self
+            .call_site_refs
+            .lock()
+            .unwrap()
+            .entry(call_site_key)
+            .or_insert_with(|| Arc::new(self.create_call_site_ref(call_site)))
+            .clone();
+        let callable_ref = (call_site_ref.call_hash, call_site_ref.counters.as_ref());
+        self.record_call(callable_ref, &call_site_ref.sampler, function, classify)
+    }
+
+    fn create_call_site_ref(&self, call_site: &CallSite) -> CallSiteRef {
+        let name: &str = call_site.get_name();
+        let (call_hash, counters) = self.add_named_ref(name, call_site.module);
+        CallSiteRef {
+            profiler_id: self.profiler_id,
+            call_hash,
+            counters,
+            sampler: CallSampler::new(self.call_sampling),
+        }
+    }
 
-        let mut pcall_hash = self.pcall_hash;
+    // Call sites and spans are identified by `module::name`, so a function
+    // both annotated with `#[profile]` and traced shares one entry.
+    pub(crate) fn add_named_ref(&self, name: &str, module: &str) -> (u64, Arc<CallCounters>) {
+        let identity: String = format!("{}::{}", module, name);
+        let (call_hash, counters) = self.add_call_ref(&identity, name, module, false);
+        if self.rolling_windows {
+            counters.enable_windows();
+        }
+        (call_hash, counters)
+    }
 
+    // Records a span that was timed outside of the call stack. The parent is
+    // the outermost span it ran beneath, like a primary call.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn record_span(
+        &self,
+        callable_ref: (u64, &CallCounters),
+        pcall_hash: Option<u64>,
+        time: Duration,
+    ) {
         if pcall_hash.is_none() {
-            pcall_hash = Some(call_hash);
-            self.pcall_hash = pcall_hash;
-            if !timing_refs.contains_key(&call_hash) {
-                timing_refs.insert(call_hash, HashMap::new());
-            }
-            return;
+            callable_ref.1.mark_primary();
         }
+        let time_ns: f64 = time.as_nanos() as f64;
+        self.record_sample(
+            callable_ref,
+            pcall_hash,
+            Some(time_ns),
+            CallOutcome::Returned,
+        );
+    }
 
-        let pcall_hash_value = pcall_hash.unwrap();
-        let pcall_timing = timing_refs.get_mut(&pcall_hash_value).unwrap();
-        if !pcall_timing.contains_key(&call_hash) {
-            let call_metrics = callable_refs.get(&call_hash).unwrap().clone();
-            let new_metrics = call_metrics.clone_and_reset();
-            pcall_timing.insert(call_hash, new_metrics);
+    #[cfg(feature = "tracing")]
+    pub(crate) fn get_clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    fn add_function_ref<F>(&self, location: &Location) -> (u64, Arc<CallCounters>)
+    where
+        F: 'static,
+    {
+        let function_name: &str = type_name::<F>();
+        let (module, name) = split_type_name(function_name);
+
+        // Closures share a type name with every other closure in the same
+        // function, so the source location they were wrapped at keeps them
+        // apart without making the identity depend on the build.
+        let mut identity: String = function_name.to_string();
+        if function_name.contains("{{closure}}") {
+            identity = format!("{}@{}", function_name, location);
         }
+
+        let separate: bool = self.call_identity == CallIdentity::Separate;
+        self.add_call_ref(&identity, &name, &module, separate)
     }
 
-    fn add_call_ref<F, A, R>(&mut self, call: &F) -> u64
+    #[track_caller]
+    pub fn function_wrapper<F, A, R>(&self, function: F) -> impl Fn(A) -> R + '_
     where
         F: Fn(A) -> R + Send + Sync + 'static,
         A: Send + Sync,
         R: 'static,
     {
-        let type_id: TypeId = TypeId::of::<F>();
-        let call_hash: u64 = self.hash_type_id(type_id);
-        let call_metrics: CallableMetrics = self.create_callable_metrics(call, call_hash);
-        let mut callable_refs: MutexGuard<HashMap<u64, CallableMetrics>> =
-            self.callable_refs.lock().unwrap();
-        callable_refs.insert(call_hash, call_metrics);
-        call_hash
+        self.sampled_function_wrapper(self.call_sampling, function)
     }
 
-    pub fn function_wrapper<F, A, R>(&mut self, function: F) -> impl FnMut(A) -> R + '_
+    #[track_caller]
+    pub fn sampled_function_wrapper<F, A, R>(
+        &self,
+        sampling: CallSampling,
+        function: F,
+    ) -> impl Fn(A) -> R + '_
     where
         F: Fn(A) -> R + Send + Sync + 'static,
         A: Send + Sync,
         R: 'static,
     {
-        let call_hash: u64 = self.add_call_ref(&function);
+        let location: &Location = Location::caller();
+        let register = || self.add_function_ref::<F>(location);
+        self.wrap_call(register, sampling, function, |_: &R| CallOutcome::Returned)
+    }
 
-        move |arg: A| {
-            self.set_pcall_hash(call_hash);
-            let start_time: Instant = Instant::now();
-            let result: R = function(arg);
-            let elapsed_time: Duration = start_time.elapsed();
-            self.append_metrics(call_hash, elapsed_time);
-            result
+    #[track_caller]
+    pub fn result_wrapper<F, A, T, E>(&self, function: F) -> impl Fn(A) -> Result<T, E> + '_
+    where
+        F: Fn(A) -> Result<T, E> + Send + Sync + 'static,
+        A: Send + Sync,
+        T: 'static,
+        E: 'static,
+    {
+        let location: &Location = Location::caller();
+        let register = || self.add_function_ref::<F>(location);
+        self.wrap_call(register, self.call_sampling, function, classify_result)
+    }
+
+    pub fn keyed_function_wrapper<F, A, R>(&self, key: &str, function: F) -> impl Fn(A) -> R + '_
+    where
+        F: Fn(A) -> R + Send + Sync + 'static,
+        A: Send + Sync,
+        R: 'static,
+    {
+        let (module, _) = split_type_name(type_name::<F>());
+        let identity: String = format!("key:{}", key);
+        let key: String = key.to_string();
+        let register = move || self.add_call_ref(&identity, &key, &module, false);
+        let classify = |_: &R| CallOutcome::Returned;
+        self.wrap_call(register, self.call_sampling, function, classify)
+    }
+}
+
+impl Drop for TimeProfiler {
+    fn drop(&mut self) {
+        recorder::drop_profiler(self.profiler_id);
+    }
+}
+
+fn classify_result<T, E>(result: &Result<T, E>) -> CallOutcome {
+    match result {
+        Ok(_) => CallOutcome::Ok,
+        Err(_) => CallOutcome::Err,
+    }
+}
+
+fn hash_identity(identity: &str) -> u64 {
+    // FNV-1a keeps call hashes stable across runs and Rust versions.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in identity.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn split_type_name(type_name: &str) -> (String, String) {
+    let mut segments: Vec<String> = Vec::new();
+    let mut segment: String = String::new();
+    let mut depth: usize = 0;
+    let mut chars = type_name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            ':' if depth == 0 && chars.peek() == Some(&':') => {
+                chars.next();
+                segments.push(std::mem::take(&mut segment));
+                continue;
+            }
+            _ => {}
         }
+        segment.push(c);
+    }
+    segments.push(segment);
+
+    let mut name_start: usize = segments.len() - 1;
+    while name_start > 0 && segments[name_start].starts_with("{{") {
+        name_start -= 1;
     }
+    let module: String = segments[..name_start].join("::");
+    let name: String = segments[name_start..].join("::");
+    (module, name)
 }
@@ -0,0 +1,358 @@
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use super::metrics::{CallOutcome, CallableMetrics};
+use super::profiler::CallSampling;
+use super::window::{RollingWindows, WindowStatistics, ROLLING_WINDOWS};
+
+// The recording path takes a lock only in amortised, rare cases. Totals are
+// accumulated in atomic counters owned by each wrapper, call stacks live in a
+// thread-local, and the per-primary subcall counters are cached per thread.
+// A thread locks the profiler's `timing_refs` on the first lookup of each
+// subcall, and locks `LIVE_PROFILERS` once on its next subcall after any
+// profiler is dropped, including the throwaway profilers of `calibrate`.
+// Counters are merged into `CallableMetrics` only when read.
+
+static NEXT_PROFILER_ID: AtomicUsize = AtomicUsize::new(0);
+static LIVE_PROFILERS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+static DROPPED_PROFILERS: AtomicUsize = AtomicUsize::new(0);
+
+type SubcallKey = (usize, u64, u64);
+
+#[derive(Default)]
+struct SubcallCache {
+    dropped_profilers: usize,
+    counters: HashMap<SubcallKey, Arc<CallCounters>, BuildHasherDefault<KeyHasher>>,
+}
+
+impl SubcallCache {
+    // Entries of dropped profilers are evicted by each thread the next time
+    // it records a subcall, since a thread-local cannot be cleared from the
+    // thread dropping the profiler.
+    fn evict_dropped(&mut self) {
+        let dropped_profilers: usize = DROPPED_PROFILERS.load(Ordering::Acquire);
+        if self.dropped_profilers == dropped_profilers {
+            return;
+        }
+        let live_profilers = LIVE_PROFILERS.lock().unwrap();
+        self.counters
+            .retain(|(profiler_id, _, _), _| live_profilers.contains(profiler_id));
+        self.dropped_profilers = dropped_profilers;
+    }
+}
+
+thread_local! {
+    static CALL_STACK: RefCell<Vec<CallFrame>> = const { RefCell::new(Vec::new()) };
+    static SUBCALL_CACHE: RefCell<SubcallCache> = RefCell::new(SubcallCache::default());
+    static SAMPLE_STATE: Cell<u64> = const { Cell::new(0) };
+}
+
+pub(crate) fn next_profiler_id() -> usize {
+    let profiler_id: usize = NEXT_PROFILER_ID.fetch_add(1, Ordering::Relaxed);
+    LIVE_PROFILERS.lock().unwrap().insert(profiler_id);
+    profiler_id
+}
+
+pub(crate) fn drop_profiler(profiler_id: usize) {
+    LIVE_PROFILERS.lock().unwrap().remove(&profiler_id);
+    DROPPED_PROFILERS.fetch_add(1, Ordering::Release);
+}
+
+#[derive(Default)]
+pub(crate) struct CallCounters {
+    primary: AtomicBool,
+    ncalls: AtomicU64,
+    timed_calls: AtomicU64,
+    time_ns: AtomicU64,
+    time_sq_ns: AtomicU64,
+    npanics: AtomicU64,
+    ok_calls: AtomicU64,
+    ok_timed_calls: AtomicU64,
+    ok_time_ns: AtomicU64,
+    err_calls: AtomicU64,
+    err_timed_calls: AtomicU64,
+    err_time_ns: AtomicU64,
+    windows: OnceLock<RollingWindows>,
+}
+
+impl CallCounters {
+    // Timed calls add their raw time, and are scaled up to all calls only
+    // when read. Untimed calls are still counted, so call and outcome counts
+    // stay exact when sampling.
+    pub(crate) fn record(&self, time_ns: Option<f64>, outcome: CallOutcome) {
+        self.ncalls.fetch_add(1, Ordering::Relaxed);
+        let time_ns: f64 = match time_ns {
+            Some(time_ns) => time_ns,
+            None => return self.record_outcome(outcome),
+        };
+        let time_sq_ns: f64 = time_ns * time_ns;
+        let time_ns: u64 = time_ns.round() as u64;
+        self.timed_calls.fetch_add(1, Ordering::Relaxed);
+        self.time_ns.fetch_add(time_ns, Ordering::Relaxed);
+        let _ = self
+            .time_sq_ns
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + time_sq_ns).to_bits())
+            });
+        match outcome {
+            CallOutcome::Returned => {}
+            CallOutcome::Ok => {
+                self.ok_calls.fetch_add(1, Ordering::Relaxed);
+                self.ok_timed_calls.fetch_add(1, Ordering::Relaxed);
+                self.ok_time_ns.fetch_add(time_ns, Ordering::Relaxed);
+            }
+            CallOutcome::Err => {
+                self.err_calls.fetch_add(1, Ordering::Relaxed);
+                self.err_timed_calls.fetch_add(1, Ordering::Relaxed);
+                self.err_time_ns.fetch_add(time_ns, Ordering::Relaxed);
+            }
+            CallOutcome::Panicked => {
+                self.npanics.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn record_outcome(&self, outcome: CallOutcome) {
+        let counter: &AtomicU64 = match outcome {
+            CallOutcome::Returned => return,
+            CallOutcome::Ok => &self.ok_calls,
+            CallOutcome::Err => &self.err_calls,
+            CallOutcome::Panicked => &self.npanics,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn mark_primary(&self) {
+        if !self.primary.load(Ordering::Relaxed) {
+            self.primary.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn enable_windows(&self) {
+        self.windows.get_or_init(RollingWindows::new);
+    }
+
+    pub(crate) fn record_window(&self, now: Duration, time_ns: Option<f64>) {
+        if let Some(windows) = self.windows.get() {
+            windows.record(now, time_ns);
+        }
+    }
+
+    pub(crate) fn get_window_statistics(&self, now: Duration) -> Vec<WindowStatistics> {
+        let windows: &RollingWindows = match self.windows.get() {
+            Some(windows) => windows,
+            None => return Vec::new(),
+        };
+        ROLLING_WINDOWS
+            .iter()
+            .map(|window| windows.get_statistics(now, *window))
+            .collect()
+    }
+
+    pub(crate) fn is_primary(&self) -> bool {
+        self.primary.load(Ordering::Relaxed)
+    }
+
+    // Calls running concurrently with a reset may be split between the
+    // cleared and the new totals.
+    pub(crate) fn reset(&self) {
+        self.primary.store(false, Ordering::Relaxed);
+        let counters = [
+            &self.ncalls,
+            &self.timed_calls,
+            &self.time_ns,
+            &self.time_sq_ns,
+            &self.npanics,
+            &self.ok_calls,
+            &self.ok_timed_calls,
+            &self.ok_time_ns,
+            &self.err_calls,
+            &self.err_timed_calls,
+            &self.err_time_ns,
+        ];
+        for counter in counters {
+            counter.store(0, Ordering::Relaxed);
+        }
+        if let Some(windows) = self.windows.get() {
+            windows.clear();
+        }
+    }
+
+    // Sampled times are extrapolated to every call of their bucket, which
+    // stays unbiased however the calls fall between the samples.
+    pub(crate) fn merge_into(&self, call_metrics: &mut CallableMetrics) {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let (ncalls, timed_calls) = (load(&self.ncalls), load(&self.timed_calls));
+        let (ok_calls, ok_timed_calls) = (load(&self.ok_calls), load(&self.ok_timed_calls));
+        let (err_calls, err_timed_calls) = (load(&self.err_calls), load(&self.err_timed_calls));
+        let time_sq_ns: f64 = f64::from_bits(load(&self.time_sq_ns));
+
+        call_metrics.ncalls += ncalls as usize;
+        call_metrics.timed_calls += timed_calls as usize;
+        call_metrics.time_ns += extrapolate(load(&self.time_ns) as f64, ncalls, timed_calls);
+        call_metrics.time_sq_ns += extrapolate(time_sq_ns, ncalls, timed_calls);
+        call_metrics.npanics += load(&self.npanics) as usize;
+        call_metrics.ok_calls += ok_calls as usize;
+        call_metrics.ok_time_ns +=
+            extrapolate(load(&self.ok_time_ns) as f64, ok_calls, ok_timed_calls);
+        call_metrics.err_calls += err_calls as usize;
+        call_metrics.err_time_ns +=
+            extrapolate(load(&self.err_time_ns) as f64, err_calls, err_timed_calls);
+    }
+}
+
+fn extrapolate(timed_ns: f64, ncalls: u64, timed_calls: u64) -> f64 {
+    if timed_calls == 0 || timed_calls >= ncalls {
+        return timed_ns;
+    }
+    timed_ns * ncalls as f64 / timed_calls as f64
+}
+
+pub(crate) struct CallFrame {
+    pub profiler_id: usize,
+    pub call_hash: u64,
+    pub descendants: usize,
+}
+
+/// Pushes a frame and returns the primary call of the same profiler that
+/// this call is nested under, if any.
+pub(crate) fn push_frame(profiler_id: usize, call_hash: u64) -> Option<u64> {
+    CALL_STACK.with(|call_stack| {
+        let mut call_stack = call_stack.borrow_mut();
+        let pcall_hash: Option<u64> = call_stack
+            .iter()
+            .find(|frame| frame.profiler_id == profiler_id)
+            .map(|frame| frame.call_hash);
+        call_stack.push(CallFrame {
+            profiler_id,
+            call_hash,
+            descendants: 0,
+        });
+        pcall_hash
+    })
+}
+
+// Only timed calls count as descendants, since untimed calls skip the clock
+// reads that make up most of the calibrated overhead.
+pub(crate) fn pop_frame(profiler_id: usize, timed: bool) -> CallFrame {
+    CALL_STACK.with(|call_stack| {
+        let mut call_stack = call_stack.borrow_mut();
+        let call_frame: CallFrame = call_stack.pop().unwrap();
+        let parent_frame = call_stack
+            .iter_mut()
+            .rev()
+            .find(|frame| frame.profiler_id == profiler_id);
+        if let Some(parent_frame) = parent_frame {
+            parent_frame.descendants += call_frame.descendants + timed as usize;
+        }
+        call_frame
+    })
+}
+
+pub(crate) fn record_subcall<L>(
+    key: SubcallKey,
+    time_ns: Option<f64>,
+    outcome: CallOutcome,
+    lookup: L,
+) where
+    L: FnOnce() -> Arc<CallCounters>,
+{
+    SUBCALL_CACHE.with(|subcall_cache| {
+        let mut subcall_cache = subcall_cache.borrow_mut();
+        subcall_cache.evict_dropped();
+        let counters = subcall_cache.counters.entry(key).or_insert_with(lookup);
+        counters.record(time_ns, outcome);
+    })
+}
+
+pub(crate) struct CallSampler {
+    sampling: CallSampling,
+    calls: AtomicU64,
+}
+
+impl CallSampler {
+    pub(crate) fn new(sampling: CallSampling) -> CallSampler {
+        match sampling {
+            CallSampling::All => {}
+            CallSampling::EveryNth(n) => assert!(n > 0, "Sampling interval must be non-zero"),
+            CallSampling::Fraction(fraction) => assert!(
+                fraction > 0.0 && fraction <= 1.0,
+                "Sampling fraction must be in (0, 1]"
+            ),
+        }
+        CallSampler {
+            sampling,
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    /// Decides whether the next call is timed.
+    pub(crate) fn sample(&self) -> bool {
+        match self.sampling {
+            CallSampling::All => true,
+            CallSampling::EveryNth(n) => {
+                let index: u64 = self.calls.fetch_add(1, Ordering::Relaxed);
+                index.is_multiple_of(n)
+            }
+            CallSampling::Fraction(fraction) => next_random() < fraction,
+        }
+    }
+}
+
+// Thread-local xorshift64* generator, returns a value in [0, 1).
+pub(crate) fn next_random() -> f64 {
+    SAMPLE_STATE.with(|sample_state| {
+        let mut state: u64 = sample_state.get();
+        if state == 0 {
+            state = random_seed();
+        }
+        let random: f64 = next_seeded_random(&mut state);
+        sample_state.set(state);
+        random
+    })
+}
+
+pub(crate) fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+// The same generator on a caller-owned state, for reproducible sequences.
+pub(crate) fn next_seeded_random(state: &mut u64) -> f64 {
+    if *state == 0 {
+        *state = 0x9e3779b97f4a7c15;
+    }
+    *state ^= *state >> 12;
+    *state ^= *state << 25;
+    *state ^= *state >> 27;
+    (state.wrapping_mul(0x2545f4914f6cdd1d) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[derive(Default)]
+struct KeyHasher {
+    hash: u64,
+}
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ value).wrapping_mul(0x517cc1b727220a95);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
@@ -0,0 +1,142 @@
+use core::fmt::Debug;
+use std::fmt::{Formatter, Result};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::metrics::TimeFormatterNs;
+
+// The whole timer state lives in a single word so that every transition is
+// one compare-and-swap. The high bit is the running flag. While paused the
+// remaining bits hold the elapsed nanoseconds; while running they hold the
+// biased instant (relative to `epoch`) at which the timer would have started
+// to have accumulated its current duration.
+const RUNNING_BIT: u64 = 1 << 63;
+const VALUE_MASK: u64 = RUNNING_BIT - 1;
+const START_BIAS: u64 = 1 << 61;
+
+pub struct SharedTimerModule {
+    epoch: Instant,
+    state: AtomicU64,
+}
+
+impl SharedTimerModule {
+    pub fn new() -> Self {
+        SharedTimerModule {
+            epoch: Instant::now(),
+            state: AtomicU64::new(0),
+        }
+    }
+
+    pub fn start(&self) -> &Self {
+        self.transition(|state, now_ns| {
+            if state & RUNNING_BIT != 0 {
+                return state;
+            }
+            Self::running_state(now_ns, state)
+        });
+        self
+    }
+
+    pub fn pause(&self) -> &Self {
+        self.transition(Self::elapsed_ns);
+        self
+    }
+
+    pub fn reset(&self) -> &Self {
+        self.state.store(0, Ordering::SeqCst);
+        self
+    }
+
+    pub fn refresh(&self) -> &Self {
+        let now_ns: u64 = self.now_ns();
+        self.state
+            .store(Self::running_state(now_ns, 0), Ordering::SeqCst);
+        self
+    }
+
+    /// Times beyond what the state word can hold are clamped: about 292
+    /// years while paused and 73 years while running.
+    pub fn set_time(&self, time_seconds: u64) -> &Self {
+        let duration_ns: u128 = Duration::new(time_seconds, 0).as_nanos();
+        let duration_ns: u64 = duration_ns.min(VALUE_MASK as u128) as u64;
+        self.transition(|state, now_ns| {
+            if state & RUNNING_BIT != 0 {
+                return Self::running_state(now_ns, duration_ns.min(START_BIAS));
+            }
+            duration_ns
+        });
+        self
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state.load(Ordering::SeqCst) & RUNNING_BIT != 0
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        let state: u64 = self.state.load(Ordering::SeqCst);
+        let elapsed_ns: u64 = Self::elapsed_ns(state, self.now_ns());
+        Duration::from_nanos(elapsed_ns)
+    }
+
+    pub fn get_time(&self) -> f64 {
+        self.get_duration().as_secs_f64()
+    }
+
+    pub fn get_time_ms(&self) -> f64 {
+        self.get_duration().as_millis() as f64
+    }
+
+    pub fn get_string(&self) -> String {
+        let formatter: TimeFormatterNs = TimeFormatterNs::new_from_duration(self.get_duration());
+        let formatted_time: String = formatter.auto_format();
+        formatted_time
+    }
+
+    fn now_ns(&self) -> u64 {
+        self.epoch.elapsed().as_nanos() as u64
+    }
+
+    fn running_state(now_ns: u64, elapsed_ns: u64) -> u64 {
+        let start_ns: u64 = (START_BIAS + now_ns).saturating_sub(elapsed_ns);
+        (start_ns & VALUE_MASK) | RUNNING_BIT
+    }
+
+    fn elapsed_ns(state: u64, now_ns: u64) -> u64 {
+        if state & RUNNING_BIT == 0 {
+            return state;
+        }
+        let start_ns: u64 = state & VALUE_MASK;
+        (START_BIAS + now_ns).saturating_sub(start_ns)
+    }
+
+    fn transition<T>(&self, update: T)
+    where
+        T: Fn(u64, u64) -> u64,
+    {
+        let mut state: u64 = self.state.load(Ordering::SeqCst);
+        loop {
+            let new_state: u64 = update(state, self.now_ns());
+            match self.state.compare_exchange_weak(
+                state,
+                new_state,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return,
+                Err(current) => state = current,
+            }
+        }
+    }
+}
+
+impl Default for SharedTimerModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for SharedTimerModule {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}", self.get_string())
+    }
+}
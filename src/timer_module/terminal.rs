@@ -28,6 +28,7 @@ impl ANSICode {
 pub struct Terminal {
     ansi_color: ANSICode,
     ansi_reset: ANSICode,
+    output: Option<String>,
 }
 
 impl Terminal {
@@ -38,16 +39,39 @@ impl Terminal {
         Terminal {
             ansi_color,
             ansi_reset,
+            output: None,
         }
     }
 
-    pub fn write(&self, text: &str) {
+    /// Collects written lines without colors instead of printing them.
+    pub fn buffered() -> Terminal {
+        let mut terminal: Terminal = Terminal::new();
+        terminal.output = Some(String::new());
+        terminal
+    }
+
+    pub fn write(&mut self, text: &str) {
+        if let Some(output) = self.output.as_mut() {
+            output.push_str(text);
+            output.push('\n');
+            return;
+        }
         let ansi_color_val: &str = self.ansi_color.value();
         let ansi_reset_val: &str = self.ansi_reset.value();
         println!("{}{}{}", ansi_color_val, text, ansi_reset_val);
     }
 
+    pub fn take_output(&mut self) -> String {
+        self.output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     pub fn set_ansi_color(&mut self, ansi_color: ANSICode) {
         self.ansi_color = ansi_color;
     }
 }
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,142 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReportTheme {
+    pub header_box: String,
+    pub header_rule: String,
+    pub field_separator: String,
+    pub line_separator: String,
+    pub call_rule: String,
+    pub primary_rule: String,
+    pub total_rule: String,
+    pub trailing_lines: usize,
+    pub thousands_separator: Option<char>,
+    pub decimal_mark: char,
+    pub ascii_units: bool,
+}
+
+impl ReportTheme {
+    pub fn unicode() -> ReportTheme {
+        ReportTheme {
+            header_box: "█".to_string(),
+            header_rule: "=".to_string(),
+            field_separator: " — ".to_string(),
+            line_separator: "\n".to_string(),
+            call_rule: "——".to_string(),
+            primary_rule: "——————".to_string(),
+            total_rule: "―――".to_string(),
+            trailing_lines: 3,
+            thousands_separator: None,
+            decimal_mark: '.',
+            ascii_units: false,
+        }
+    }
+
+    pub fn ascii() -> ReportTheme {
+        ReportTheme {
+            header_box: "#".to_string(),
+            header_rule: "=".to_string(),
+            field_separator: " - ".to_string(),
+            line_separator: "\n".to_string(),
+            call_rule: "--".to_string(),
+            primary_rule: "------".to_string(),
+            total_rule: "---".to_string(),
+            trailing_lines: 3,
+            thousands_separator: Some(','),
+            decimal_mark: '.',
+            ascii_units: true,
+        }
+    }
+
+    pub fn compact() -> ReportTheme {
+        ReportTheme {
+            header_box: String::new(),
+            header_rule: String::new(),
+            field_separator: " | ".to_string(),
+            line_separator: " | ".to_string(),
+            call_rule: String::new(),
+            primary_rule: String::new(),
+            total_rule: String::new(),
+            trailing_lines: 0,
+            thousands_separator: None,
+            decimal_mark: '.',
+            ascii_units: false,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ReportTheme> {
+        match name {
+            "unicode" => Some(ReportTheme::unicode()),
+            "ascii" => Some(ReportTheme::ascii()),
+            "compact" => Some(ReportTheme::compact()),
+            _ => None,
+        }
+    }
+
+    pub fn set_thousands_separator(&mut self, separator: Option<char>) -> &mut Self {
+        self.thousands_separator = separator;
+        self
+    }
+
+    pub fn set_decimal_mark(&mut self, decimal_mark: char) -> &mut Self {
+        self.decimal_mark = decimal_mark;
+        self
+    }
+
+    pub fn format_count(&self, count: usize) -> String {
+        let digits: String = count.to_string();
+        let separator: char = match self.thousands_separator {
+            Some(separator) => separator,
+            None => return digits,
+        };
+
+        let mut grouped: String = String::new();
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    pub fn format_decimal(&self, number: &str) -> String {
+        if self.decimal_mark == '.' {
+            return number.to_string();
+        }
+        number.replace('.', &self.decimal_mark.to_string())
+    }
+
+    pub fn format_header(&self, title: &str) -> String {
+        let mut header: String = title.to_string();
+        if !self.header_box.is_empty() {
+            header = format!("{} {} {}", self.header_box, title, self.header_box);
+        }
+        if self.header_rule.is_empty() {
+            return header;
+        }
+        // Measured in bytes, as the report has always been, so the box
+        // characters of the unicode theme widen the rule.
+        let rule: String = self.header_rule.repeat(header.len());
+        format!("{}\n{}", header, rule)
+    }
+
+    pub fn format_total(&self, body: &str) -> String {
+        let mut total: String = body.to_string();
+        if !self.total_rule.is_empty() {
+            total = format!("{} {} {}", self.total_rule, body, self.total_rule);
+        }
+        format!("{}{}", total, "\n".repeat(self.trailing_lines))
+    }
+
+    pub fn append_rule(&self, body: String, rule: &str) -> String {
+        if rule.is_empty() {
+            return body;
+        }
+        format!("{}\n{}", body, rule)
+    }
+}
+
+impl Default for ReportTheme {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::clock::Clock;
+use super::metrics::TimeFormatterNs;
+use super::timer::TimerModule;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    Burst,
+    Delay,
+    Skip,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tick {
+    pub index: usize,
+    pub scheduled: Duration,
+    pub actual: Duration,
+    pub missed: usize,
+}
+
+impl Tick {
+    pub fn get_jitter_ns(&self) -> f64 {
+        self.actual.as_nanos() as f64 - self.scheduled.as_nanos() as f64
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TickStatistics {
+    pub ticks: usize,
+    pub missed_ticks: usize,
+    pub mean_jitter_ns: f64,
+    pub min_jitter_ns: f64,
+    pub max_jitter_ns: f64,
+    m2_jitter_ns: f64,
+}
+
+impl TickStatistics {
+    pub fn get_jitter_stddev_ns(&self) -> f64 {
+        if self.ticks > 1 {
+            return (self.m2_jitter_ns / (self.ticks - 1) as f64).sqrt();
+        }
+        0.0
+    }
+
+    pub fn get_string(&self) -> String {
+        let mean = TimeFormatterNs::new(self.mean_jitter_ns).auto_format();
+        let max = TimeFormatterNs::new(self.max_jitter_ns).auto_format();
+        let stddev = TimeFormatterNs::new(self.get_jitter_stddev_ns()).auto_format();
+        format!(
+            "Ticks: [{}] — Missed: [{}]\nJitter: [{}] — Max: [{}] — StdDev: [{}]",
+            self.ticks, self.missed_ticks, mean, max, stddev
+        )
+    }
+
+    fn record(&mut self, tick: &Tick) {
+        let jitter_ns: f64 = tick.get_jitter_ns();
+        if self.ticks == 0 {
+            self.min_jitter_ns = jitter_ns;
+            self.max_jitter_ns = jitter_ns;
+        }
+        self.ticks += 1;
+        self.missed_ticks = self.missed_ticks.saturating_add(tick.missed);
+        self.min_jitter_ns = self.min_jitter_ns.min(jitter_ns);
+        self.max_jitter_ns = self.max_jitter_ns.max(jitter_ns);
+
+        let delta: f64 = jitter_ns - self.mean_jitter_ns;
+        self.mean_jitter_ns += delta / self.ticks as f64;
+        self.m2_jitter_ns += delta * (jitter_ns - self.mean_jitter_ns);
+    }
+}
+
+pub struct Ticker {
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+    drift_correction: bool,
+    timer_module: TimerModule,
+    next_tick: Duration,
+    index: usize,
+    statistics: TickStatistics,
+}
+
+impl Ticker {
+    pub fn new(period: Duration) -> Self {
+        assert!(!period.is_zero(), "Ticker period must be non-zero");
+        Ticker {
+            period,
+            missed_tick_behavior: MissedTickBehavior::Burst,
+            drift_correction: true,
+            timer_module: TimerModule::new(),
+            next_tick: Duration::new(0, 0),
+            index: 0,
+            statistics: TickStatistics::default(),
+        }
+    }
+
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) -> &mut Self {
+        self.missed_tick_behavior = behavior;
+        self
+    }
+
+    pub fn set_drift_correction(&mut self, drift_correction: bool) -> &mut Self {
+        self.drift_correction = drift_correction;
+        self
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.timer_module.set_clock(clock);
+        self.reset()
+    }
+
+    pub fn start(&mut self) -> &mut Self {
+        self.timer_module.start();
+        self
+    }
+
+    pub fn pause(&mut self) -> &mut Self {
+        self.timer_module.pause();
+        self
+    }
+
+    pub fn reset(&mut self) -> &mut Self {
+        self.timer_module.reset();
+        self.next_tick = Duration::new(0, 0);
+        self.index = 0;
+        self.statistics = TickStatistics::default();
+        self
+    }
+
+    pub fn get_period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn get_statistics(&self) -> &TickStatistics {
+        &self.statistics
+    }
+
+    pub fn tick(&mut self) -> Tick {
+        self.start();
+
+        let mut elapsed: Duration = self.timer_module.get_duration();
+        if elapsed < self.next_tick {
+            let clock: &Arc<dyn Clock> = self.timer_module.get_clock();
+            clock.sleep(self.next_tick - elapsed);
+            elapsed = self.timer_module.get_duration();
+        }
+
+        let scheduled: Duration = self.next_tick;
+        let missed: usize = self.schedule_next_tick(scheduled, elapsed);
+        let tick = Tick {
+            index: self.index,
+            scheduled,
+            actual: elapsed,
+            missed,
+        };
+        self.index += 1;
+        self.statistics.record(&tick);
+        tick
+    }
+
+    fn schedule_next_tick(&mut self, scheduled: Duration, elapsed: Duration) -> usize {
+        // A clock may wake from `sleep` before the tick is due.
+        let lag: Duration = elapsed.saturating_sub(scheduled);
+        if lag < self.period {
+            self.next_tick = scheduled + self.period;
+            if !self.drift_correction {
+                self.next_tick = elapsed + self.period;
+            }
+            return 0;
+        }
+
+        // Burst delivers every late tick back-to-back, so nothing is missed.
+        let missed_ticks: u128 = lag.as_nanos() / self.period.as_nanos();
+        let missed: usize = usize::try_from(missed_ticks).unwrap_or(usize::MAX);
+        match self.missed_tick_behavior {
+            MissedTickBehavior::Burst => {
+                self.next_tick = scheduled + self.period;
+                0
+            }
+            MissedTickBehavior::Delay => {
+                self.next_tick = elapsed + self.period;
+                missed
+            }
+            MissedTickBehavior::Skip => {
+                // The first period boundary after `elapsed`, saturating
+                // rather than overflowing after an extremely long stall.
+                let skipped_ns: u128 = self.period.as_nanos() * (missed_ticks + 1);
+                let skipped_ns: u64 = u64::try_from(skipped_ns).unwrap_or(u64::MAX);
+                self.next_tick = scheduled.saturating_add(Duration::from_nanos(skipped_ns));
+                missed
+            }
+        }
+    }
+}
+
+impl Iterator for Ticker {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        Some(self.tick())
+    }
+}
@@ -1,22 +1,77 @@
 use core::fmt::Debug;
 use std::fmt::{Formatter, Result};
-use std::time::{Duration, Instant};
+#[cfg(feature = "tracing")]
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use super::metrics::TimeFormatterNs;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::clock::{Clock, MonotonicClock};
+use super::metrics::{TimeFormat, TimeFormatterNs};
 
 pub struct TimerModule {
     is_running: bool,
-    start_time: Instant,
+    clock: Arc<dyn Clock>,
+    // The clock time up to which `duration` has been accumulated.
+    start_time: Duration,
     duration: Duration,
+    laps: Vec<Duration>,
+    time_format: TimeFormat,
+}
+
+/// A timer that emits a `tracing` event with its elapsed time when dropped.
+/// It dereferences to the `TimerModule` it wraps.
+#[cfg(feature = "tracing")]
+pub struct LoggedTimer {
+    timer_module: TimerModule,
+    label: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimerSnapshot {
+    pub duration: Duration,
+    pub is_running: bool,
+    pub laps: Vec<Duration>,
+    pub timestamp: SystemTime,
+}
+
+impl TimerSnapshot {
+    /// Elapsed time as of now, including the wall-clock time that passed
+    /// since the snapshot was taken if the timer was running.
+    pub fn get_duration(&self) -> Duration {
+        if !self.is_running {
+            return self.duration;
+        }
+        let since_snapshot: Duration = SystemTime::now()
+            .duration_since(self.timestamp)
+            .unwrap_or_default();
+        self.duration + since_snapshot
+    }
 }
 
 impl TimerModule {
     pub fn new() -> Self {
         TimerModule {
             is_running: false,
-            start_time: Instant::now(),
+            clock: Arc::new(MonotonicClock::new()),
+            start_time: Duration::new(0, 0),
             duration: Duration::new(0, 0),
+            laps: Vec::new(),
+            time_format: TimeFormat::new(),
+        }
+    }
+
+    pub fn from_snapshot(snapshot: &TimerSnapshot) -> Self {
+        let mut timer_module: TimerModule = TimerModule::new();
+        timer_module.duration = snapshot.get_duration();
+        timer_module.laps = snapshot.laps.clone();
+        if snapshot.is_running {
+            timer_module.start();
         }
+        timer_module
     }
 
     pub fn start(&mut self) -> &mut Self {
@@ -32,9 +87,10 @@ impl TimerModule {
     }
 
     pub fn reset(&mut self) -> &mut Self {
-        self.start_time = Instant::now();
+        self.start_time = self.clock.now();
         self.duration = Duration::new(0, 0);
         self.is_running = false;
+        self.laps.clear();
         self
     }
 
@@ -46,10 +102,68 @@ impl TimerModule {
 
     pub fn set_time(&mut self, time_seconds: u64) -> &mut Self {
         self.duration = Duration::new(time_seconds, 0);
-        self.start_time = Instant::now() - self.duration;
+        self.start_time = self.clock.now();
         self
     }
 
+    /// Times the timer with another clock, keeping the duration so far.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.update_duration();
+        self.clock = clock;
+        self.start_time = self.clock.now();
+        self
+    }
+
+    pub fn get_clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    pub fn set_time_format(&mut self, time_format: TimeFormat) -> &mut Self {
+        self.time_format = time_format;
+        self
+    }
+
+    /// Wraps the timer in a guard that emits a `tracing` event with the
+    /// elapsed time when it is dropped.
+    #[cfg(feature = "tracing")]
+    pub fn set_log_on_drop(self, label: &str) -> LoggedTimer {
+        LoggedTimer {
+            timer_module: self,
+            label: label.to_string(),
+        }
+    }
+
+    pub fn lap(&mut self) -> Duration {
+        self.update_duration();
+        let lapped: Duration = self.laps.iter().sum();
+        let lap: Duration = self.duration.saturating_sub(lapped);
+        self.laps.push(lap);
+        lap
+    }
+
+    pub fn get_laps(&self) -> &[Duration] {
+        &self.laps
+    }
+
+    pub fn snapshot(&mut self) -> TimerSnapshot {
+        self.update_duration();
+        TimerSnapshot {
+            duration: self.duration,
+            is_running: self.is_running,
+            laps: self.laps.clone(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    pub fn get_duration(&mut self) -> Duration {
+        self.update_duration();
+        self.duration
+    }
+
     pub fn get_time(&mut self) -> f64 {
         self.update_duration();
         self.duration.as_secs_f64()
@@ -62,28 +176,68 @@ impl TimerModule {
 
     pub fn get_string(&mut self) -> String {
         self.update_duration();
-        let formatter: TimeFormatterNs = TimeFormatterNs::new_from_duration(self.duration);
+        let formatter: TimeFormatterNs =
+            TimeFormatterNs::new_from_duration(self.duration).with_format(self.time_format);
         let formatted_time: String = formatter.auto_format();
         formatted_time
     }
 
     fn update_start_time(&mut self) {
         if !self.is_running {
-            self.start_time = Instant::now() - self.duration;
+            self.start_time = self.clock.now();
         }
     }
 
     fn update_duration(&mut self) {
         if self.is_running {
-            self.duration = Instant::now() - self.start_time;
+            let now: Duration = self.clock.now();
+            self.duration += now.saturating_sub(self.start_time);
+            self.start_time = now;
         }
     }
 }
 
+impl Default for TimerModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Deref for LoggedTimer {
+    type Target = TimerModule;
+
+    fn deref(&self) -> &TimerModule {
+        &self.timer_module
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl DerefMut for LoggedTimer {
+    fn deref_mut(&mut self) -> &mut TimerModule {
+        &mut self.timer_module
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for LoggedTimer {
+    fn drop(&mut self) {
+        let elapsed: Duration = get_duration(&self.timer_module);
+        tracing::info!(
+            label = %self.label,
+            elapsed_ns = elapsed.as_nanos() as u64,
+            "timed block {} took {:?}",
+            self.label,
+            self.timer_module
+        );
+    }
+}
+
 fn get_duration(time_module: &TimerModule) -> Duration {
     let mut duration: Duration = time_module.duration;
     if time_module.is_running {
-        duration = Instant::now() - time_module.start_time;
+        let now: Duration = time_module.clock.now();
+        duration += now.saturating_sub(time_module.start_time);
     }
     duration
 }
@@ -91,7 +245,8 @@ fn get_duration(time_module: &TimerModule) -> Duration {
 impl Debug for TimerModule {
     fn fmt(&self, f: &mut Formatter) -> Result {
         let duration: Duration = get_duration(self);
-        let formatter: TimeFormatterNs = TimeFormatterNs::new_from_duration(duration);
+        let formatter: TimeFormatterNs =
+            TimeFormatterNs::new_from_duration(duration).with_format(self.time_format);
         let formatted_time: String = formatter.auto_format();
         write!(f, "{}", formatted_time)
     }
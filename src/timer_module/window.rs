@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+pub const ROLLING_WINDOWS: [Duration; 3] = [
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+];
+
+const WINDOW_SLOTS: u64 = 61;
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const MAX_BUCKET_POWER: u32 = 40;
+const HISTOGRAM_BUCKETS: usize = ((MAX_BUCKET_POWER - 2) as u64 * SUB_BUCKETS) as usize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowStatistics {
+    pub window: Duration,
+    pub ncalls: u64,
+    pub calls_per_sec: f64,
+    pub mean_ns: f64,
+    pub p50_ns: f64,
+    pub p90_ns: f64,
+    pub p99_ns: f64,
+}
+
+struct WindowSlot {
+    second: AtomicU64,
+    calls: AtomicU64,
+    samples: AtomicU64,
+    time_ns: AtomicU64,
+    histogram: Vec<AtomicU32>,
+}
+
+impl WindowSlot {
+    fn new() -> WindowSlot {
+        WindowSlot {
+            second: AtomicU64::new(0),
+            calls: AtomicU64::new(0),
+            samples: AtomicU64::new(0),
+            time_ns: AtomicU64::new(0),
+            histogram: (0..HISTOGRAM_BUCKETS).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    fn clear(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.samples.store(0, Ordering::Relaxed);
+        self.time_ns.store(0, Ordering::Relaxed);
+        for bucket in self.histogram.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+// One slot per second of the longest window plus the running second,
+// reused round-robin. Windows only cover completed seconds, so the rate of
+// the "last 1s" is not skewed by a second that just started. A slot is
+// cleared by the first call that lands in it in a new second; calls racing
+// with that clear may be dropped, which rolling statistics tolerate.
+pub(crate) struct RollingWindows {
+    slots: Vec<WindowSlot>,
+}
+
+impl RollingWindows {
+    pub(crate) fn new() -> RollingWindows {
+        RollingWindows {
+            slots: (0..WINDOW_SLOTS).map(|_| WindowSlot::new()).collect(),
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.second.store(0, Ordering::Release);
+        }
+    }
+
+    // Every call is counted towards the rate; only timed calls are samples
+    // of the mean and percentiles.
+    pub(crate) fn record(&self, now: Duration, time_ns: Option<f64>) {
+        let second: u64 = now.as_secs() + 1;
+        let slot: &WindowSlot = &self.slots[(second % WINDOW_SLOTS) as usize];
+        let slot_second: u64 = slot.second.load(Ordering::Acquire);
+        if slot_second < second
+            && slot
+                .second
+                .compare_exchange(slot_second, second, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        {
+            slot.clear();
+        }
+
+        slot.calls.fetch_add(1, Ordering::Relaxed);
+        let time_ns: u64 = match time_ns {
+            Some(time_ns) => time_ns.round() as u64,
+            None => return,
+        };
+        slot.samples.fetch_add(1, Ordering::Relaxed);
+        slot.time_ns.fetch_add(time_ns, Ordering::Relaxed);
+        slot.histogram[bucket_index(time_ns)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get_statistics(&self, now: Duration, window: Duration) -> WindowStatistics {
+        let second: u64 = now.as_secs() + 1;
+        let window_secs: u64 = window.as_secs().clamp(1, WINDOW_SLOTS - 1);
+        let first_second: u64 = second.saturating_sub(window_secs).max(1);
+
+        let mut statistics = WindowStatistics {
+            window,
+            ..WindowStatistics::default()
+        };
+        let mut samples: u64 = 0;
+        let mut time_ns: u64 = 0;
+        let mut histogram: Vec<u64> = vec![0; HISTOGRAM_BUCKETS];
+        for slot_second in first_second..second {
+            let slot: &WindowSlot = &self.slots[(slot_second % WINDOW_SLOTS) as usize];
+            if slot.second.load(Ordering::Acquire) != slot_second {
+                continue;
+            }
+            statistics.ncalls += slot.calls.load(Ordering::Relaxed);
+            samples += slot.samples.load(Ordering::Relaxed);
+            time_ns += slot.time_ns.load(Ordering::Relaxed);
+            for (total, bucket) in histogram.iter_mut().zip(slot.histogram.iter()) {
+                *total += bucket.load(Ordering::Relaxed) as u64;
+            }
+        }
+
+        let completed_secs: u64 = second - first_second;
+        if completed_secs > 0 {
+            statistics.calls_per_sec = statistics.ncalls as f64 / completed_secs as f64;
+        }
+        if samples > 0 {
+            statistics.mean_ns = time_ns as f64 / samples as f64;
+            statistics.p50_ns = percentile(&histogram, samples, 0.50);
+            statistics.p90_ns = percentile(&histogram, samples, 0.90);
+            statistics.p99_ns = percentile(&histogram, samples, 0.99);
+        }
+        statistics
+    }
+}
+
+// Log-linear buckets: values below SUB_BUCKETS get their own bucket, every
+// larger power of two is split into SUB_BUCKETS buckets (~6% error at the
+// bucket midpoint).
+fn bucket_index(time_ns: u64) -> usize {
+    if time_ns < SUB_BUCKETS {
+        return time_ns as usize;
+    }
+    let power: u32 = (63 - time_ns.leading_zeros()).min(MAX_BUCKET_POWER);
+    if power == MAX_BUCKET_POWER {
+        return HISTOGRAM_BUCKETS - 1;
+    }
+    let sub_bucket: u64 = (time_ns >> (power - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    ((power - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS + sub_bucket) as usize
+}
+
+fn bucket_midpoint(index: usize) -> f64 {
+    let index: u64 = index as u64;
+    if index < SUB_BUCKETS {
+        return index as f64;
+    }
+    let shift: u64 = index / SUB_BUCKETS - 1;
+    let low: u64 = (SUB_BUCKETS + index % SUB_BUCKETS) << shift;
+    low as f64 + (1u64 << shift) as f64 / 2.0
+}
+
+fn percentile(histogram: &[u64], samples: u64, quantile: f64) -> f64 {
+    let rank: u64 = ((samples as f64 * quantile).ceil() as u64).max(1);
+    let mut seen: u64 = 0;
+    for (index, count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return bucket_midpoint(index);
+        }
+    }
+    bucket_midpoint(histogram.len() - 1)
+}
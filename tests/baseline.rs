@@ -0,0 +1,204 @@
+#![cfg(not(feature = "disable-profiling"))]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use timer_module::baseline::ProfileBaseline;
+use timer_module::clock::ManualClock;
+use timer_module::comparison::{BaselineComparison, Verdict};
+use timer_module::profiler::TimeProfiler;
+
+fn profile_run(delays_ns: &[u64]) -> TimeProfiler {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+
+    let wrapped = profiler.keyed_function_wrapper("work", move |n: u64| {
+        clock.advance(Duration::from_nanos(n));
+    });
+    for delay_ns in delays_ns {
+        wrapped(*delay_ns);
+    }
+    drop(wrapped);
+    profiler
+}
+
+#[test]
+fn baseline_round_trips_through_text() {
+    let baseline: ProfileBaseline = profile_run(&[90, 110, 100]).get_baseline();
+    let parsed: ProfileBaseline = ProfileBaseline::parse(&baseline.get_string()).unwrap();
+
+    assert_eq!(parsed.calls.len(), 1);
+    let (call, parsed_call) = (&baseline.calls[0], &parsed.calls[0]);
+    assert_eq!(parsed_call.name, "work");
+    assert_eq!(parsed_call.ncalls, call.ncalls);
+    assert_eq!(parsed_call.time_ns, call.time_ns);
+    assert_eq!(parsed_call.get_percall_stddev(), call.get_percall_stddev());
+}
+
+#[test]
+fn comparison_flags_only_significant_changes() {
+    let baseline: ProfileBaseline = profile_run(&[90, 110, 100, 95, 105]).get_baseline();
+
+    let slower: BaselineComparison = profile_run(&[190, 210, 200]).compare_baseline(&baseline, 2.0);
+    assert_eq!(slower.calls[0].verdict, Verdict::Regressed);
+    assert!(slower.has_regression(50.0));
+    assert!(!slower.has_regression(150.0));
+
+    let noisy: BaselineComparison = profile_run(&[20, 200]).compare_baseline(&baseline, 2.0);
+    assert_eq!(noisy.calls[0].verdict, Verdict::Unchanged);
+}
+
+fn closure_run(fast_ns: u64, slow_ns: u64) -> TimeProfiler {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+
+    let fast_clock: Arc<ManualClock> = clock.clone();
+    let fast = profiler.function_wrapper(move |_: ()| {
+        fast_clock.advance(Duration::from_nanos(fast_ns));
+    });
+    let slow = profiler.function_wrapper(move |_: ()| {
+        clock.advance(Duration::from_nanos(slow_ns));
+    });
+    for _ in 0..3 {
+        fast(());
+        slow(());
+    }
+    drop((fast, slow));
+    profiler
+}
+
+#[test]
+fn closures_of_one_function_match_by_identity() {
+    let baseline: ProfileBaseline = closure_run(10, 1_000).get_baseline();
+    assert_eq!(baseline.calls[0].name, baseline.calls[1].name);
+
+    let comparison: BaselineComparison = closure_run(10, 2_000).compare_baseline(&baseline, 2.0);
+    assert_eq!(comparison.calls.len(), 2);
+    assert!(comparison.get_ambiguous().is_empty());
+    let mut changes: Vec<(f64, f64)> = comparison
+        .calls
+        .iter()
+        .map(|call| {
+            let baseline_ns: f64 = call.baseline.as_ref().unwrap().get_percall_time();
+            (
+                baseline_ns,
+                call.current.as_ref().unwrap().get_percall_time(),
+            )
+        })
+        .collect();
+    changes.sort_by(|a, b| a.0.total_cmp(&b.0));
+    assert_eq!(changes, vec![(10.0, 10.0), (1_000.0, 2_000.0)]);
+}
+
+#[test]
+fn shared_names_without_identity_are_reported_ambiguous() {
+    let mut baseline: ProfileBaseline = closure_run(10, 1_000).get_baseline();
+    for call in baseline.calls.iter_mut() {
+        call.call_hash = call.call_hash.wrapping_add(1);
+    }
+
+    let comparison: BaselineComparison = closure_run(10, 1_000).compare_baseline(&baseline, 2.0);
+    let verdicts: Vec<(Verdict, bool)> = comparison
+        .calls
+        .iter()
+        .map(|call| (call.verdict, call.ambiguous))
+        .collect();
+    assert_eq!(
+        verdicts,
+        vec![
+            (Verdict::Added, true),
+            (Verdict::Added, true),
+            (Verdict::Removed, true),
+            (Verdict::Removed, true),
+        ]
+    );
+
+    // A name held by one callable is still matched when its hash changed.
+    let mut baseline: ProfileBaseline = profile_run(&[100]).get_baseline();
+    baseline.calls[0].call_hash = baseline.calls[0].call_hash.wrapping_add(1);
+    let comparison: BaselineComparison = profile_run(&[100]).compare_baseline(&baseline, 2.0);
+    assert_eq!(comparison.calls.len(), 1);
+    assert_eq!(comparison.calls[0].verdict, Verdict::Unchanged);
+}
+
+fn inner_step(clock: Arc<ManualClock>) {
+    clock.advance(Duration::from_nanos(10));
+}
+
+fn shard_run(calls: usize) -> &'static TimeProfiler {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(profiler));
+
+    let inner = profiler.function_wrapper(inner_step);
+    let outer = profiler.keyed_function_wrapper("shard", move |_: ()| {
+        inner(clock.clone());
+        inner(clock.clone());
+    });
+    for _ in 0..calls {
+        outer(());
+    }
+    profiler
+}
+
+#[test]
+fn merged_profiles_sum_calls_and_subcalls() {
+    let merged: &TimeProfiler = shard_run(1);
+    merged.merge_profiler(shard_run(2));
+    let saved: ProfileBaseline =
+        ProfileBaseline::parse(&shard_run(3).get_baseline().get_string()).unwrap();
+    merged.merge_profile(&saved);
+
+    let profile: ProfileBaseline = merged.get_baseline();
+    let totals: Vec<(&str, usize, f64)> = profile
+        .calls
+        .iter()
+        .map(|call| (call.name.as_str(), call.ncalls, call.time_ns))
+        .collect();
+    assert_eq!(totals, vec![("inner_step", 12, 120.0), ("shard", 6, 120.0)]);
+
+    assert_eq!(profile.subcalls.len(), 1);
+    let subcalls = profile.subcalls.values().next().unwrap();
+    assert_eq!(subcalls.len(), 1);
+    assert_eq!((subcalls[0].ncalls, subcalls[0].time_ns), (12, 120.0));
+
+    let mut offline: ProfileBaseline = shard_run(1).get_baseline();
+    offline.merge(&shard_run(2).get_baseline());
+    let ncalls: Vec<usize> = offline.calls.iter().map(|call| call.ncalls).collect();
+    assert_eq!(ncalls, vec![6, 3]);
+    assert_eq!(offline.subcalls.values().next().unwrap()[0].ncalls, 6);
+}
+
+#[test]
+fn leaf_primaries_survive_save_load_and_merge() {
+    let saved: String = profile_run(&[100, 200]).get_baseline().get_string();
+    let parsed: ProfileBaseline = ProfileBaseline::parse(&saved).unwrap();
+    assert!(parsed.is_primary(parsed.calls[0].call_hash));
+
+    let merged: TimeProfiler = TimeProfiler::new(false);
+    merged.merge_profile(&parsed);
+    let profile: ProfileBaseline = merged.get_baseline();
+    assert_eq!(profile.calls.len(), 1);
+    assert!(profile.is_primary(profile.calls[0].call_hash));
+    assert!(merged.get_report_string().contains("work"));
+}
+
+#[test]
+fn merged_calls_with_colliding_hashes_are_rehashed() {
+    let mut merged: ProfileBaseline = profile_run(&[100]).get_baseline();
+    let mut other: ProfileBaseline = profile_run(&[200]).get_baseline();
+    other.calls[0].name = String::from("other_work");
+    assert_eq!(merged.calls[0].call_hash, other.calls[0].call_hash);
+
+    merged.merge(&other);
+    assert_eq!(merged.calls.len(), 2);
+    assert_ne!(merged.calls[0].call_hash, merged.calls[1].call_hash);
+    assert!(merged
+        .calls
+        .iter()
+        .all(|call| merged.is_primary(call.call_hash)));
+    assert_eq!(merged.subcalls.len(), 2);
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use timer_module::benchmark::{Benchmark, BenchmarkSummary, Outliers};
+
+fn outlier_samples() -> Vec<f64> {
+    let mut samples: Vec<f64> = (10..26).map(|sample| sample as f64).collect();
+    samples.extend([60.0, -5.0, 40.0, -20.0]);
+    samples
+}
+
+#[test]
+fn summary_statistics_and_tukey_outliers() {
+    let mut benchmark: Benchmark = Benchmark::new("samples");
+    benchmark.set_seed(7);
+    let summary: BenchmarkSummary = benchmark.summarize(outlier_samples(), 2_000);
+
+    assert_eq!(summary.iterations, 2_000);
+    assert_eq!(summary.mean_ns, 17.75);
+    assert_eq!(summary.median_ns, 17.5);
+    assert!((summary.stddev_ns - 15.498302114136653).abs() < 1e-9);
+    assert_eq!((summary.min_ns, summary.max_ns), (-20.0, 60.0));
+
+    // Quartiles 12.75 and 22.25: mild fences at -1.5 and 36.5, severe
+    // fences at -15.75 and 50.75.
+    let outliers = Outliers {
+        low_severe: 1,
+        low_mild: 1,
+        high_mild: 1,
+        high_severe: 1,
+    };
+    assert_eq!(summary.outliers, outliers);
+    assert_eq!(summary.outliers.get_count(), 4);
+}
+
+#[test]
+fn seeded_bootstrap_interval_is_reproducible() {
+    let mut benchmark: Benchmark = Benchmark::new("samples");
+    benchmark.set_seed(42);
+    let first: BenchmarkSummary = benchmark.summarize(outlier_samples(), 20);
+    let second: BenchmarkSummary = benchmark.summarize(outlier_samples(), 20);
+
+    assert_eq!(first.mean_lower_ns, second.mean_lower_ns);
+    assert_eq!(first.mean_upper_ns, second.mean_upper_ns);
+    assert!(first.mean_lower_ns < first.mean_ns && first.mean_ns < first.mean_upper_ns);
+
+    // A narrower confidence level gives a narrower interval.
+    benchmark.set_confidence_level(0.5);
+    let narrow: BenchmarkSummary = benchmark.summarize(outlier_samples(), 20);
+    assert!(narrow.mean_lower_ns > first.mean_lower_ns);
+    assert!(narrow.mean_upper_ns < first.mean_upper_ns);
+}
+
+#[test]
+fn constant_samples_have_an_exact_interval_and_no_outliers() {
+    let mut benchmark: Benchmark = Benchmark::new("constant");
+    benchmark.set_seed(1);
+    let summary: BenchmarkSummary = benchmark.summarize(vec![5.0; 10], 10);
+
+    assert_eq!((summary.mean_lower_ns, summary.mean_upper_ns), (5.0, 5.0));
+    assert_eq!(summary.stddev_ns, 0.0);
+    assert_eq!(summary.outliers, Outliers::default());
+}
+
+#[test]
+fn run_collects_the_configured_samples() {
+    let mut benchmark: Benchmark = Benchmark::new("sum");
+    benchmark
+        .set_warm_up_time(Duration::from_millis(5))
+        .set_measurement_time(Duration::from_millis(20))
+        .set_sample_count(10)
+        .set_seed(3);
+    let summary: BenchmarkSummary =
+        benchmark.run_with_input(vec![1u64; 64], |values| values.iter().sum::<u64>());
+
+    assert_eq!(summary.name, "sum");
+    assert_eq!(summary.samples.len(), 10);
+    assert_eq!(summary.iterations % 10, 0);
+    assert!(summary.min_ns <= summary.median_ns && summary.median_ns <= summary.max_ns);
+}
@@ -0,0 +1,95 @@
+#![cfg(not(feature = "disable-profiling"))]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use timer_module::assert_budget;
+use timer_module::budget::{BudgetReport, BudgetStatus, PerformanceBudget};
+use timer_module::clock::ManualClock;
+use timer_module::profiler::TimeProfiler;
+
+fn profile_run() -> TimeProfiler {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+
+    let wrapped = profiler.keyed_function_wrapper("search", move |_: ()| {
+        clock.advance(Duration::from_nanos(400));
+    });
+    for _ in 0..10 {
+        wrapped(());
+    }
+    drop(wrapped);
+    profiler
+}
+
+#[test]
+fn budget_report_lists_each_limit() {
+    let profiler: TimeProfiler = profile_run();
+    let mut budget: PerformanceBudget = PerformanceBudget::new();
+    budget
+        .add_percall_limit("search", Duration::from_nanos(500))
+        .add_total_limit("search", Duration::from_micros(2))
+        .add_percall_limit("missing", Duration::from_nanos(500));
+
+    let report: BudgetReport = profiler.check_budget(&budget);
+    let statuses: Vec<BudgetStatus> = report.results.iter().map(|r| r.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            BudgetStatus::Passed,
+            BudgetStatus::Exceeded,
+            BudgetStatus::Missing
+        ]
+    );
+    assert_eq!(report.get_failures().len(), 2);
+    assert_budget!(profiler, "search", per_call < Duration::from_nanos(500));
+}
+
+#[test]
+#[should_panic(expected = "performance budget exceeded")]
+fn assert_budget_panics_when_exceeded() {
+    let profiler: TimeProfiler = profile_run();
+    assert_budget!(profiler, "search", total < Duration::from_micros(2));
+}
+
+mod nested {
+    use timer_module::profiler::TimeProfiler;
+
+    pub fn record_lookup(profiler: &TimeProfiler) {
+        let wrapped = profiler.keyed_function_wrapper("lookup", |n: u64| n + 1);
+        wrapped(1);
+    }
+}
+
+#[test]
+fn budgets_match_keys_closures_and_module_suffixes() {
+    let profiler: TimeProfiler = profile_run();
+    nested::record_lookup(&profiler);
+    let unkeyed = profiler.function_wrapper(|n: u64| n + 1);
+    unkeyed(1);
+
+    let mut budget: PerformanceBudget = PerformanceBudget::new();
+    budget
+        .add_total_limit("budget::search", Duration::from_millis(1))
+        .add_total_limit("nested::lookup", Duration::from_millis(1))
+        .add_total_limit("budget::nested::lookup", Duration::from_millis(1))
+        .add_total_limit("other::lookup", Duration::from_millis(1))
+        .add_total_limit(
+            "budgets_match_keys_closures_and_module_suffixes::{{closure}}",
+            Duration::from_millis(1),
+        );
+
+    let report: BudgetReport = profiler.check_budget(&budget);
+    let statuses: Vec<BudgetStatus> = report.results.iter().map(|r| r.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            BudgetStatus::Passed,
+            BudgetStatus::Passed,
+            BudgetStatus::Passed,
+            BudgetStatus::Missing,
+            BudgetStatus::Passed
+        ]
+    );
+}
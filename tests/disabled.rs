@@ -0,0 +1,15 @@
+#![cfg(feature = "disable-profiling")]
+
+use timer_module::profiler::TimeProfiler;
+
+#[test]
+fn disabled_profiling_calls_functions_directly() {
+    let profiler: TimeProfiler = TimeProfiler::new(false);
+    let wrapped = profiler.function_wrapper(|n: u64| n * 2);
+    let wrapped_result = profiler.result_wrapper(|text: &str| text.parse::<u64>());
+
+    assert_eq!(wrapped(21), 42);
+    assert!(wrapped_result("x").is_err());
+    assert!(profiler.get_callables().is_empty());
+    assert_eq!(profiler.get_overhead(), None);
+}
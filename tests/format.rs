@@ -0,0 +1,72 @@
+use timer_module::metrics::{TimeFormat, TimeFormatterNs, TimeStyle, TimeUnit};
+
+fn format(nanos: f64, style: TimeStyle, precision: usize) -> String {
+    let mut time_format: TimeFormat = TimeFormat::new();
+    time_format.set_style(style).set_precision(precision);
+    TimeFormatterNs::new(nanos)
+        .with_format(time_format)
+        .auto_format()
+}
+
+#[test]
+fn auto_format_keeps_seconds_as_the_largest_default_unit() {
+    let format_default = |nanos: f64| TimeFormatterNs::new(nanos).auto_format();
+    assert_eq!(format_default(0.0), "0.00ns");
+    assert_eq!(format_default(999.0), "999.00ns");
+    assert_eq!(format_default(1_500.0), "1.50μs");
+    assert_eq!(format_default(2.5e6), "2.50ms");
+    assert_eq!(format_default(125.3e9), "125.30s");
+    assert_eq!(format_default(2.0 * 86_400e9), "172800.00s");
+
+    let mut time_format: TimeFormat = TimeFormat::new();
+    time_format.set_max_unit(TimeUnit::Days).set_ascii(true);
+    let format_days = |nanos: f64| {
+        TimeFormatterNs::new(nanos)
+            .with_format(time_format)
+            .auto_format()
+    };
+    assert_eq!(format_days(1_500.0), "1.50us");
+    assert_eq!(format_days(90e9), "1.50m");
+    assert_eq!(format_days(36.0 * 3_600e9), "1.50d");
+}
+
+#[test]
+fn compound_style_splits_and_carries() {
+    let compound = |nanos: f64, precision: usize| format(nanos, TimeStyle::Compound, precision);
+    assert_eq!(compound(0.0, 2), "0.00s");
+    assert_eq!(compound(0.4, 2), "0.00s");
+    assert_eq!(compound(0.4, 9), "0.000000000s");
+    assert_eq!(compound(60e9, 1), "1m 00.0s");
+    assert_eq!(compound(3_600e9, 1), "1h 00m 00.0s");
+    assert_eq!(compound(86_400e9, 0), "1d 00h 00m 00s");
+    assert_eq!(compound(59.96e9, 2), "59.96s");
+    assert_eq!(compound(59.96e9, 1), "1m 00.0s");
+    assert_eq!(compound(3_599.96e9, 1), "1h 00m 00.0s");
+    assert_eq!(compound(3_725.3e9, 1), "1h 02m 05.3s");
+    assert_eq!(compound(-61e9, 0), "-1m 01s");
+}
+
+#[test]
+fn clock_style_rounds_to_milliseconds() {
+    let clock = |nanos: f64| format(nanos, TimeStyle::Clock, 2);
+    assert_eq!(clock(0.0), "00:00:00.000");
+    assert_eq!(clock(0.4), "00:00:00.000");
+    assert_eq!(clock(60e9), "00:01:00.000");
+    assert_eq!(clock(3_600e9), "01:00:00.000");
+    assert_eq!(clock(86_400e9), "24:00:00.000");
+    assert_eq!(clock(59.9996e9), "00:01:00.000");
+    assert_eq!(clock(3_725.3e9), "01:02:05.300");
+}
+
+#[test]
+fn iso8601_style_omits_empty_components() {
+    let iso = |nanos: f64| format(nanos, TimeStyle::Iso8601, 2);
+    assert_eq!(iso(0.0), "PT0S");
+    assert_eq!(iso(0.4), "PT0S");
+    assert_eq!(iso(0.6), "PT0.000000001S");
+    assert_eq!(iso(60e9), "PT1M");
+    assert_eq!(iso(3_600e9), "PT1H");
+    assert_eq!(iso(86_400e9), "PT24H");
+    assert_eq!(iso(59.96e9), "PT59.96S");
+    assert_eq!(iso(3_725.3e9), "PT1H2M5.3S");
+}
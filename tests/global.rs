@@ -0,0 +1,33 @@
+#![cfg(not(feature = "disable-profiling"))]
+
+// The global profiler can be installed once per process, so this test binary
+// holds nothing else that touches it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use timer_module::clock::ManualClock;
+use timer_module::profiler::{CallIdentity, TimeProfiler};
+
+#[test]
+fn global_profiler_can_be_installed_once() {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler
+        .set_clock(clock.clone())
+        .set_call_identity(CallIdentity::Separate);
+    assert!(TimeProfiler::set_global(profiler).is_ok());
+    assert!(TimeProfiler::set_global(TimeProfiler::new(false)).is_err());
+
+    let wrapped = TimeProfiler::global().keyed_function_wrapper("global", move |n: u64| {
+        clock.advance(Duration::from_nanos(n))
+    });
+    wrapped(10);
+    assert_eq!(
+        TimeProfiler::global()
+            .find_callable("global")
+            .unwrap()
+            .time_ns,
+        10.0
+    );
+}
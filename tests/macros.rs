@@ -0,0 +1,140 @@
+#![cfg(all(feature = "macros", not(feature = "disable-profiling")))]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use timer_module::budget::{BudgetStatus, PerformanceBudget};
+use timer_module::metrics::CallableMetrics;
+use timer_module::profile;
+use timer_module::profiler::{CallSampling, TimeProfiler};
+
+fn profiler() -> &'static TimeProfiler {
+    static PROFILER: OnceLock<TimeProfiler> = OnceLock::new();
+    PROFILER.get_or_init(|| TimeProfiler::new(false))
+}
+
+#[profile(profiler = profiler())]
+fn parse_digit(digit: char) -> Result<u32, String> {
+    digit.to_digit(10).ok_or(format!("not a digit: {}", digit))
+}
+
+#[profile(profiler = profiler(), name = "sum")]
+fn sum_digits(digits: &str) -> u32 {
+    digits
+        .chars()
+        .filter_map(|digit| parse_digit(digit).ok())
+        .sum()
+}
+
+#[profile]
+fn globally_profiled(n: u64) -> u64 {
+    n * 2
+}
+
+#[test]
+fn profile_attribute_records_calls_with_the_profiler() {
+    assert_eq!(sum_digits("12x4"), 7);
+    assert_eq!(sum_digits("9"), 9);
+
+    let sum: CallableMetrics = profiler().find_callable("macros::sum").unwrap();
+    assert_eq!(sum.ncalls, 2);
+    let parse_digit: CallableMetrics = profiler().find_callable("parse_digit").unwrap();
+    assert_eq!((parse_digit.ok_calls, parse_digit.err_calls), (4, 1));
+
+    let children: Vec<String> = profiler()
+        .get_children("sum")
+        .into_iter()
+        .map(|child| child.name)
+        .collect();
+    assert_eq!(children, vec!["parse_digit"]);
+
+    let mut budget: PerformanceBudget = PerformanceBudget::new();
+    budget
+        .add_total_limit("sum", Duration::from_secs(1))
+        .add_total_limit("macros::parse_digit", Duration::from_secs(1));
+    let statuses: Vec<BudgetStatus> = profiler()
+        .check_budget(&budget)
+        .results
+        .iter()
+        .map(|result| result.status)
+        .collect();
+    assert_eq!(statuses, vec![BudgetStatus::Passed, BudgetStatus::Passed]);
+
+    assert_eq!(globally_profiled(21), 42);
+    let global: CallableMetrics = TimeProfiler::global()
+        .find_callable("globally_profiled")
+        .unwrap();
+    assert_eq!(global.ncalls, 1);
+}
+
+struct Digits;
+
+impl Digits {
+    #[profile(profiler = profiler())]
+    fn checked_parse(digits: &str) -> Result<u32, String> {
+        digits
+            .parse()
+            .map_err(|_| format!("not digits: {}", digits))
+    }
+}
+
+struct Letters;
+
+impl Letters {
+    #[profile(profiler = profiler())]
+    fn checked_parse(letters: &str) -> Result<String, String> {
+        match letters.chars().all(char::is_alphabetic) {
+            true => Ok(letters.to_string()),
+            false => Err(format!("not letters: {}", letters)),
+        }
+    }
+}
+
+#[test]
+fn profile_attribute_names_methods_after_their_type() {
+    assert_eq!(Digits::checked_parse("42"), Ok(42));
+    assert!(Digits::checked_parse("4x").is_err());
+    assert!(Letters::checked_parse("abc").is_ok());
+
+    let digits: CallableMetrics = profiler()
+        .find_callable("macros::Digits::checked_parse")
+        .unwrap();
+    assert_eq!(
+        (digits.name.as_str(), digits.module.as_str()),
+        ("Digits::checked_parse", "macros")
+    );
+    assert_eq!((digits.ok_calls, digits.err_calls), (1, 1));
+    let letters: CallableMetrics = profiler().find_callable("Letters::checked_parse").unwrap();
+    assert_eq!((letters.ok_calls, letters.err_calls), (1, 0));
+}
+
+static USE_SAMPLED: AtomicBool = AtomicBool::new(false);
+
+fn switched_profiler() -> &'static TimeProfiler {
+    static FIRST: OnceLock<TimeProfiler> = OnceLock::new();
+    static SAMPLED: OnceLock<TimeProfiler> = OnceLock::new();
+    if !USE_SAMPLED.load(Ordering::Relaxed) {
+        return FIRST.get_or_init(|| TimeProfiler::new(false));
+    }
+    SAMPLED.get_or_init(|| {
+        let mut profiler: TimeProfiler = TimeProfiler::new(false);
+        profiler.set_call_sampling(CallSampling::EveryNth(4));
+        profiler
+    })
+}
+
+#[profile(profiler = switched_profiler())]
+fn switched_step() {}
+
+#[test]
+fn call_sites_sample_consistently_with_a_second_profiler() {
+    switched_step();
+    USE_SAMPLED.store(true, Ordering::Relaxed);
+    for _ in 0..8 {
+        switched_step();
+    }
+
+    let step: CallableMetrics = switched_profiler().find_callable("switched_step").unwrap();
+    assert_eq!((step.ncalls, step.timed_calls), (8, 2));
+}
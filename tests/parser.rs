@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use timer_module::metrics::{TimeFormat, TimeFormatterNs, TimeStyle, TimeUnit};
+use timer_module::parser::{parse_duration, ParseErrorKind};
+
+const SAMPLES_NS: [u64; 9] = [
+    0,
+    1,
+    999,
+    1_500,
+    2_500_000,
+    59_960_000_000,
+    3_725_300_000_000,
+    86_400_000_000_000,
+    90_061_123_456_789,
+];
+
+fn format(nanos: u64, time_format: TimeFormat) -> String {
+    TimeFormatterNs::new(nanos as f64)
+        .with_format(time_format)
+        .auto_format()
+}
+
+fn parse_nanos(input: &str) -> u128 {
+    parse_duration(input)
+        .unwrap_or_else(|error| panic!("{}", error))
+        .as_nanos()
+}
+
+#[test]
+fn exact_styles_round_trip() {
+    let mut compound: TimeFormat = TimeFormat::new();
+    compound.set_style(TimeStyle::Compound).set_precision(9);
+    let mut iso8601: TimeFormat = TimeFormat::new();
+    iso8601.set_style(TimeStyle::Iso8601);
+    let mut nanoseconds: TimeFormat = TimeFormat::new();
+    nanoseconds.set_unit(TimeUnit::Nanoseconds).set_precision(0);
+
+    for nanos in SAMPLES_NS {
+        for time_format in [compound, iso8601, nanoseconds] {
+            let formatted: String = format(nanos, time_format);
+            assert_eq!(parse_nanos(&formatted), nanos as u128, "{}", formatted);
+        }
+    }
+}
+
+#[test]
+fn rounded_styles_round_trip_within_their_precision() {
+    let mut clock: TimeFormat = TimeFormat::new();
+    clock.set_style(TimeStyle::Clock);
+    let mut ascii_days: TimeFormat = TimeFormat::new();
+    ascii_days
+        .set_max_unit(TimeUnit::Days)
+        .set_ascii(true)
+        .set_precision(3);
+
+    for nanos in SAMPLES_NS {
+        let formatted: String = format(nanos, TimeFormat::new());
+        let unit: TimeUnit = TimeUnit::from_nanos(nanos as f64);
+        let tolerance: f64 = unit.get_nanos().min(1e9) * 0.005;
+        let parsed: u128 = parse_nanos(&formatted);
+        assert!(
+            (parsed as f64 - nanos as f64).abs() <= tolerance,
+            "{}",
+            formatted
+        );
+
+        let formatted: String = format(nanos, ascii_days);
+        let tolerance: f64 = unit.get_nanos() * 0.0005;
+        let parsed: u128 = parse_nanos(&formatted);
+        assert!(
+            (parsed as f64 - nanos as f64).abs() <= tolerance,
+            "{}",
+            formatted
+        );
+
+        let formatted: String = format(nanos, clock);
+        let parsed: u128 = parse_nanos(&formatted);
+        assert!(
+            (parsed as f64 - nanos as f64).abs() <= 0.5e6,
+            "{}",
+            formatted
+        );
+    }
+}
+
+#[test]
+fn long_fractions_round_instead_of_overflowing() {
+    let day: Duration = Duration::from_secs(86_400);
+    assert_eq!(parse_duration("0.999999999999999999999999999999d"), Ok(day));
+    assert_eq!(
+        parse_duration("0.99999999999999999999999999h"),
+        Ok(Duration::from_secs(3_600))
+    );
+    assert_eq!(
+        parse_duration("0.000000000000000000000000000001d"),
+        Ok(Duration::ZERO)
+    );
+    assert_eq!(
+        parse_duration("1.00000000049999999999999999999s"),
+        Ok(Duration::from_secs(1))
+    );
+    assert_eq!(parse_duration("1.0000000005s"), Ok(Duration::new(1, 1)));
+    let many_digits: String = format!("0.{}d", "9".repeat(1_000));
+    assert_eq!(parse_duration(&many_digits), Ok(day));
+}
+
+#[test]
+fn out_of_range_durations_are_overflow_errors() {
+    for input in [
+        "18446744073709551616s",
+        "999999999999999999999999999999999999999d",
+        "213503982334602d",
+        "PT5124095576030432H",
+    ] {
+        let kind: ParseErrorKind = parse_duration(input).unwrap_err().kind;
+        assert_eq!(kind, ParseErrorKind::Overflow, "{}", input);
+    }
+}
@@ -0,0 +1,477 @@
+#![cfg(not(feature = "disable-profiling"))]
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use timer_module::clock::ManualClock;
+use timer_module::metrics::CallableMetrics;
+use timer_module::profiler::{
+    CallIdentity, CallOrder, CallOverhead, CallSampling, ProfileSnapshot, ProfileTotals,
+    TimeProfiler,
+};
+use timer_module::window::WindowStatistics;
+
+fn mock_profiler() -> (TimeProfiler, Arc<ManualClock>) {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+    (profiler, clock)
+}
+
+fn single_metrics(profiler: &TimeProfiler) -> CallableMetrics {
+    let callable_metrics = profiler.get_callable_metrics();
+    assert_eq!(callable_metrics.len(), 1);
+    callable_metrics.into_values().next().unwrap()
+}
+
+#[test]
+fn primary_call_is_counted_once() {
+    let (profiler, clock) = mock_profiler();
+    let wrapped = profiler.function_wrapper(move |n: u64| clock.advance(Duration::from_nanos(n)));
+
+    for _ in 0..5 {
+        wrapped(100);
+    }
+
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert_eq!(metrics.ncalls, 5);
+    assert_eq!(metrics.time_ns, 500.0);
+    assert_eq!(metrics.get_percall_time(), 100.0);
+}
+
+#[test]
+fn primary_call_has_no_subcall_entries() {
+    let (profiler, clock) = mock_profiler();
+    let wrapped = profiler.function_wrapper(move |n: u64| clock.advance(Duration::from_nanos(n)));
+    wrapped(10);
+
+    let timing_metrics = profiler.get_timing_metrics();
+    assert_eq!(timing_metrics.len(), 1);
+    assert!(timing_metrics.values().all(|subcalls| subcalls.is_empty()));
+}
+
+#[test]
+fn nested_calls_are_attributed_to_primary_call() {
+    let (profiler, clock) = mock_profiler();
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(profiler));
+
+    let inner_clock: Arc<ManualClock> = clock.clone();
+    let inner = profiler.function_wrapper(move |n: u64| {
+        inner_clock.advance(Duration::from_nanos(n));
+    });
+    let outer = profiler.function_wrapper(move |n: u64| {
+        inner(n);
+        inner(n);
+        clock.advance(Duration::from_nanos(5));
+    });
+
+    outer(10);
+    outer(10);
+
+    let callable_metrics = profiler.get_callable_metrics();
+    let timing_metrics = profiler.get_timing_metrics();
+    assert_eq!(timing_metrics.len(), 1);
+
+    let (outer_hash, subcalls) = timing_metrics.iter().next().unwrap();
+    let outer_metrics: &CallableMetrics = &callable_metrics[outer_hash];
+    assert_eq!(outer_metrics.ncalls, 2);
+    assert_eq!(outer_metrics.time_ns, 50.0);
+
+    assert_eq!(subcalls.len(), 1);
+    let (inner_hash, inner_subcall) = subcalls.iter().next().unwrap();
+    assert_eq!(inner_subcall.ncalls, 4);
+    assert_eq!(inner_subcall.time_ns, 40.0);
+
+    let inner_metrics: &CallableMetrics = &callable_metrics[inner_hash];
+    assert_eq!(inner_metrics.ncalls, 4);
+    assert_eq!(inner_metrics.time_ns, 40.0);
+}
+
+#[test]
+fn sequential_primary_calls_are_independent() {
+    let (profiler, clock) = mock_profiler();
+    let first_clock: Arc<ManualClock> = clock.clone();
+
+    let first = profiler.function_wrapper(move |n: u64| {
+        first_clock.advance(Duration::from_nanos(n));
+    });
+    first(7);
+    drop(first);
+
+    let second = profiler.function_wrapper(move |n: u64| {
+        clock.advance(Duration::from_nanos(n * 2));
+    });
+    second(7);
+    second(7);
+
+    let callable_metrics = profiler.get_callable_metrics();
+    let mut totals: Vec<(usize, f64)> = callable_metrics
+        .values()
+        .map(|metrics| (metrics.ncalls, metrics.time_ns))
+        .collect();
+    totals.sort_by_key(|total| total.0);
+    assert_eq!(totals, vec![(1, 7.0), (2, 28.0)]);
+}
+
+#[test]
+fn concurrent_calls_are_counted_exactly() {
+    let (profiler, clock) = mock_profiler();
+    let wrapped = profiler.function_wrapper(move |_: ()| clock.advance(Duration::from_nanos(1)));
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..1_000 {
+                    wrapped(());
+                }
+            });
+        }
+    });
+
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert_eq!(metrics.ncalls, 4_000);
+    assert!(profiler.get_timing_metrics().values().all(|s| s.is_empty()));
+}
+
+fn advance_clock(clock: &ManualClock) {
+    clock.advance(Duration::from_nanos(10));
+}
+
+#[test]
+fn rewrapping_a_function_preserves_metrics() {
+    let (profiler, clock) = mock_profiler();
+
+    for _ in 0..2 {
+        let wrapped = profiler.function_wrapper(advance_clock);
+        wrapped(&clock);
+    }
+
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert_eq!(metrics.name, "advance_clock");
+    assert_eq!(metrics.ncalls, 2);
+    assert_eq!(metrics.time_ns, 20.0);
+}
+
+#[test]
+fn separate_identity_keeps_wrappers_apart() {
+    let (mut profiler, clock) = mock_profiler();
+    profiler.set_call_identity(CallIdentity::Separate);
+
+    let first = profiler.function_wrapper(advance_clock);
+    let second = profiler.function_wrapper(advance_clock);
+    first(&clock);
+    second(&clock);
+    second(&clock);
+
+    let mut names: Vec<(String, usize)> = profiler
+        .get_callable_metrics()
+        .into_values()
+        .map(|metrics| (metrics.name, metrics.ncalls))
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            ("advance_clock".to_string(), 1),
+            ("advance_clock#2".to_string(), 2)
+        ]
+    );
+}
+
+fn wrap_two_closures(profiler: &TimeProfiler, clock: Arc<ManualClock>) -> Vec<u64> {
+    let first_clock: Arc<ManualClock> = clock.clone();
+    let first = profiler.function_wrapper(move |_: ()| advance_clock(&first_clock));
+    let second = profiler.function_wrapper(move |_: ()| advance_clock(&clock));
+    first(());
+    second(());
+
+    let mut call_hashes: Vec<u64> = profiler.get_callable_metrics().into_keys().collect();
+    call_hashes.sort();
+    call_hashes
+}
+
+#[test]
+fn closure_identity_is_stable_and_keeps_closures_apart() {
+    let (profiler, clock) = mock_profiler();
+    let call_hashes: Vec<u64> = wrap_two_closures(&profiler, clock);
+    assert_eq!(call_hashes.len(), 2);
+
+    // The call hashes derive from the closures' type names and source
+    // locations, so a fresh profiler assigns the same ones.
+    let (other_profiler, other_clock) = mock_profiler();
+    assert_eq!(wrap_two_closures(&other_profiler, other_clock), call_hashes);
+}
+
+#[test]
+fn keyed_wrappers_share_metrics() {
+    let (profiler, clock) = mock_profiler();
+    let first_clock: Arc<ManualClock> = clock.clone();
+
+    let first = profiler.keyed_function_wrapper("advance", move |_: ()| {
+        advance_clock(&first_clock);
+    });
+    let second = profiler.keyed_function_wrapper("advance", move |_: ()| {
+        advance_clock(&clock);
+    });
+    first(());
+    second(());
+
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert_eq!(metrics.name, "advance");
+    assert_eq!(metrics.ncalls, 2);
+}
+
+#[test]
+fn panicking_call_is_recorded_and_state_restored() {
+    let (profiler, clock) = mock_profiler();
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(profiler));
+
+    let inner_clock: Arc<ManualClock> = clock.clone();
+    let inner = profiler.function_wrapper(move |fail: bool| {
+        inner_clock.advance(Duration::from_nanos(10));
+        if fail {
+            panic!("inner failure");
+        }
+    });
+    let outer = profiler.function_wrapper(move |fail: bool| inner(fail));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| outer(true)));
+    assert!(result.is_err());
+    outer(false);
+
+    let callable_metrics = profiler.get_callable_metrics();
+    let timing_metrics = profiler.get_timing_metrics();
+    assert_eq!(timing_metrics.len(), 1);
+
+    for metrics in callable_metrics.values() {
+        assert_eq!(metrics.ncalls, 2);
+        assert_eq!(metrics.npanics, 1);
+        assert_eq!(metrics.time_ns, 20.0);
+    }
+    let subcalls = timing_metrics.values().next().unwrap();
+    let inner_subcall: &CallableMetrics = subcalls.values().next().unwrap();
+    assert_eq!(inner_subcall.ncalls, 2);
+    assert_eq!(inner_subcall.npanics, 1);
+}
+
+#[test]
+fn result_outcomes_are_timed_separately() {
+    let (profiler, clock) = mock_profiler();
+    let wrapped = profiler.result_wrapper(move |fail: bool| {
+        if fail {
+            clock.advance(Duration::from_nanos(400));
+            return Err("failed");
+        }
+        clock.advance(Duration::from_nanos(10));
+        Ok(())
+    });
+
+    for fail in [false, false, false, true, true] {
+        let _ = wrapped(fail);
+    }
+
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert_eq!(metrics.ncalls, 5);
+    assert_eq!(metrics.time_ns, 830.0);
+    assert_eq!((metrics.ok_calls, metrics.ok_time_ns), (3, 30.0));
+    assert_eq!((metrics.err_calls, metrics.err_time_ns), (2, 800.0));
+    assert_eq!(metrics.get_err_ok_ratio(), Some(40.0));
+}
+
+#[test]
+fn overhead_is_calibrated_only_on_request() {
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    assert_eq!(profiler.get_overhead(), None);
+
+    profiler.set_subtract_overhead(true);
+    let overhead: CallOverhead = profiler.get_overhead().unwrap();
+    assert!(overhead.call_ns >= overhead.timed_ns);
+
+    // A manual clock does not move during calibration, so it is skipped.
+    let (mut profiler, _) = mock_profiler();
+    profiler.calibrate().set_subtract_overhead(true);
+    assert_eq!(profiler.get_overhead(), None);
+}
+
+#[test]
+fn overhead_is_subtracted_from_calls_and_their_parents() {
+    let (mut profiler, clock) = mock_profiler();
+    profiler.set_overhead(CallOverhead {
+        timed_ns: 2.0,
+        call_ns: 5.0,
+    });
+    profiler.set_subtract_overhead(true);
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(profiler));
+
+    let inner_clock: Arc<ManualClock> = clock.clone();
+    let inner = profiler.function_wrapper(move |n: u64| {
+        inner_clock.advance(Duration::from_nanos(n));
+    });
+    let outer = profiler.function_wrapper(move |n: u64| {
+        inner(n);
+        inner(n);
+        clock.advance(Duration::from_nanos(5));
+    });
+    outer(10);
+
+    let timing_metrics = profiler.get_timing_metrics();
+    let (outer_hash, subcalls) = timing_metrics.iter().next().unwrap();
+    let inner_subcall: &CallableMetrics = subcalls.values().next().unwrap();
+    let outer_metrics: &CallableMetrics = &profiler.get_callable_metrics()[outer_hash];
+    assert_eq!(outer_metrics.time_ns, 13.0);
+    assert_eq!(inner_subcall.time_ns, 16.0);
+}
+
+#[test]
+fn sampled_calls_are_counted_exactly_and_extrapolated() {
+    let (profiler, clock) = mock_profiler();
+    let wrapped = profiler.sampled_function_wrapper(CallSampling::EveryNth(4), advance_clock);
+
+    for _ in 0..10 {
+        wrapped(&clock);
+    }
+
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert!(metrics.is_sampled());
+    assert_eq!((metrics.ncalls, metrics.timed_calls), (10, 3));
+    assert_eq!(metrics.time_ns, 100.0);
+    assert_eq!(metrics.get_percall_time(), 10.0);
+}
+
+#[test]
+fn sampled_outcomes_are_extrapolated_per_outcome() {
+    let (mut profiler, clock) = mock_profiler();
+    profiler.set_call_sampling(CallSampling::EveryNth(2));
+    let wrapped = profiler.result_wrapper(move |fail: bool| {
+        if fail {
+            clock.advance(Duration::from_nanos(100));
+            return Err("failed");
+        }
+        clock.advance(Duration::from_nanos(10));
+        Ok(())
+    });
+
+    // Calls 0, 2 and 4 are timed: two of three Ok calls and one of two Err.
+    for fail in [false, false, true, true, false] {
+        let _ = wrapped(fail);
+    }
+
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert_eq!((metrics.ncalls, metrics.timed_calls), (5, 3));
+    assert_eq!(metrics.time_ns, 200.0);
+    assert_eq!((metrics.ok_calls, metrics.ok_time_ns), (3, 30.0));
+    assert_eq!((metrics.err_calls, metrics.err_time_ns), (2, 200.0));
+    assert_eq!(metrics.get_ok_percall_time(), 10.0);
+    assert_eq!(metrics.get_err_percall_time(), 100.0);
+}
+
+#[test]
+fn snapshots_diff_and_reset_clears_metrics() {
+    let (profiler, clock) = mock_profiler();
+    let wrapped = profiler.function_wrapper(advance_clock);
+
+    wrapped(&clock);
+    let first: ProfileSnapshot = profiler.snapshot();
+    wrapped(&clock);
+    wrapped(&clock);
+    let second: ProfileSnapshot = profiler.snapshot();
+
+    let interval: ProfileSnapshot = second.diff(&first);
+    let metrics: &CallableMetrics = interval.get_callable_metrics().values().next().unwrap();
+    assert_eq!((metrics.ncalls, metrics.time_ns), (2, 20.0));
+    assert_eq!(interval.get_interval(), Duration::from_nanos(20));
+
+    profiler.reset();
+    assert_eq!(single_metrics(&profiler).ncalls, 0);
+    assert!(profiler.get_timing_metrics().is_empty());
+    wrapped(&clock);
+    assert_eq!(single_metrics(&profiler).ncalls, 1);
+    assert_eq!(profiler.snapshot().get_interval(), Duration::from_nanos(10));
+}
+
+#[test]
+fn rolling_windows_cover_completed_seconds() {
+    let (mut profiler, clock) = mock_profiler();
+    profiler.set_rolling_windows(true);
+    let wrapped = profiler.function_wrapper(advance_clock);
+
+    for _ in 0..10 {
+        wrapped(&clock);
+    }
+    assert_eq!(single_metrics(&profiler).windows[0].ncalls, 0);
+
+    clock.set(Duration::from_millis(1_500));
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    let (last_second, last_minute) = (&metrics.windows[0], &metrics.windows[2]);
+    assert_eq!((last_second.ncalls, last_second.calls_per_sec), (10, 10.0));
+    assert_eq!((last_second.mean_ns, last_second.p99_ns), (10.0, 10.5));
+    assert_eq!(last_minute.window, Duration::from_secs(60));
+
+    clock.set(Duration::from_millis(3_500));
+    let metrics: CallableMetrics = single_metrics(&profiler);
+    assert_eq!(metrics.windows[0].ncalls, 0);
+    assert_eq!(metrics.windows[1].ncalls, 10);
+}
+
+#[test]
+fn sampled_calls_are_all_counted_in_rolling_windows() {
+    let samplings = [CallSampling::EveryNth(4), CallSampling::Fraction(0.4)];
+    for sampling in samplings {
+        let (mut profiler, clock) = mock_profiler();
+        profiler
+            .set_call_sampling(sampling)
+            .set_rolling_windows(true);
+        let wrapped = profiler.function_wrapper(advance_clock);
+        for _ in 0..1_000 {
+            wrapped(&clock);
+        }
+
+        clock.set(Duration::from_millis(1_500));
+        let last_second: &WindowStatistics = &single_metrics(&profiler).windows[0];
+        assert_eq!(last_second.ncalls, 1_000, "{:?}", sampling);
+        assert_eq!(last_second.calls_per_sec, 1_000.0, "{:?}", sampling);
+        assert_eq!(last_second.mean_ns, 10.0, "{:?}", sampling);
+    }
+}
+
+#[test]
+fn query_api_finds_callables_children_and_totals() {
+    let (profiler, clock) = mock_profiler();
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(profiler));
+
+    let inner_clock: Arc<ManualClock> = clock.clone();
+    let inner = profiler.keyed_function_wrapper("inner", move |n: u64| {
+        inner_clock.advance(Duration::from_nanos(n));
+    });
+    let outer = profiler.keyed_function_wrapper("outer", move |n: u64| {
+        inner(n);
+        inner(n);
+        inner(n);
+        clock.advance(Duration::from_nanos(5));
+    });
+    outer(10);
+
+    assert_eq!(profiler.get_callables().len(), 2);
+    assert_eq!(profiler.find_callable("inner").unwrap().ncalls, 3);
+    assert!(profiler.find_callable("missing").is_none());
+
+    let children: Vec<String> = profiler
+        .get_children("outer")
+        .into_iter()
+        .map(|child| child.name)
+        .collect();
+    assert_eq!(children, vec!["inner"]);
+
+    let top: Vec<CallableMetrics> = profiler.get_top_callables(1, CallOrder::Calls);
+    assert_eq!(top[0].name, "inner");
+    let top: Vec<CallableMetrics> = profiler.get_top_callables(1, CallOrder::PerCallTime);
+    assert_eq!(top[0].name, "outer");
+
+    let totals: ProfileTotals = profiler.get_totals();
+    assert_eq!(
+        (totals.callables, totals.ncalls, totals.time_ns),
+        (2, 4, 35.0)
+    );
+}
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use timer_module::shared::SharedTimerModule;
+
+#[test]
+fn start_pause_and_reset() {
+    let timer_module: SharedTimerModule = SharedTimerModule::new();
+    assert!(!timer_module.is_running());
+    assert_eq!(timer_module.get_duration(), Duration::ZERO);
+
+    timer_module.start();
+    assert!(timer_module.is_running());
+    thread::sleep(Duration::from_millis(5));
+    timer_module.pause();
+    assert!(!timer_module.is_running());
+
+    let paused: Duration = timer_module.get_duration();
+    assert!(paused >= Duration::from_millis(5));
+    thread::sleep(Duration::from_millis(5));
+    assert_eq!(timer_module.get_duration(), paused);
+
+    // Resuming continues from the paused duration.
+    timer_module.start().start();
+    assert!(timer_module.get_duration() >= paused);
+    timer_module.pause().pause();
+    assert!(timer_module.get_duration() >= paused);
+
+    timer_module.reset();
+    assert!(!timer_module.is_running());
+    assert_eq!(timer_module.get_duration(), Duration::ZERO);
+
+    timer_module.set_time(90);
+    assert_eq!(timer_module.get_duration(), Duration::from_secs(90));
+    timer_module.refresh();
+    assert!(timer_module.is_running());
+    assert!(timer_module.get_duration() < Duration::from_secs(1));
+}
+
+#[test]
+fn concurrent_transitions_keep_elapsed_time_consistent() {
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 10_000;
+
+    let created: Instant = Instant::now();
+    let timer_module: Arc<SharedTimerModule> = Arc::new(SharedTimerModule::new());
+    let handles: Vec<_> = (0..THREADS)
+        .map(|index| {
+            let timer_module: Arc<SharedTimerModule> = timer_module.clone();
+            thread::spawn(move || {
+                for round in 0..ROUNDS {
+                    if (index + round) % 2 == 0 {
+                        timer_module.start();
+                    } else {
+                        timer_module.pause();
+                    }
+                    // Only running intervals are counted, so elapsed time
+                    // never exceeds the wall time since the timer was made.
+                    let elapsed: Duration = timer_module.get_duration();
+                    assert!(elapsed <= created.elapsed());
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    timer_module.pause();
+    let elapsed: Duration = timer_module.get_duration();
+    assert!(elapsed <= created.elapsed());
+    assert_eq!(timer_module.get_duration(), elapsed);
+
+    // Racing starts resume once: the timer is not advanced per start.
+    timer_module.reset();
+    let barrier: Arc<Barrier> = Arc::new(Barrier::new(THREADS));
+    let started: Instant = Instant::now();
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let (timer_module, barrier) = (timer_module.clone(), barrier.clone());
+            thread::spawn(move || {
+                barrier.wait();
+                timer_module.start();
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    thread::sleep(Duration::from_millis(10));
+    timer_module.pause();
+    let elapsed: Duration = timer_module.get_duration();
+    assert!(elapsed >= Duration::from_millis(10));
+    assert!(elapsed <= started.elapsed());
+}
+
+#[test]
+fn set_time_clamps_durations_the_state_cannot_hold() {
+    let timer_module: SharedTimerModule = SharedTimerModule::new();
+    timer_module.set_time(u64::MAX);
+    assert!(!timer_module.is_running());
+    assert_eq!(
+        timer_module.get_duration(),
+        Duration::from_nanos((1 << 63) - 1)
+    );
+
+    timer_module.start().set_time(u64::MAX);
+    assert!(timer_module.is_running());
+    assert!(timer_module.get_duration() >= Duration::from_nanos(1 << 61));
+    assert!(timer_module.get_duration() < Duration::from_nanos(1 << 62));
+}
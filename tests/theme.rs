@@ -0,0 +1,82 @@
+#![cfg(not(feature = "disable-profiling"))]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use timer_module::clock::ManualClock;
+use timer_module::profiler::TimeProfiler;
+use timer_module::theme::ReportTheme;
+
+fn themed_report(report_theme: Option<ReportTheme>) -> String {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+    if let Some(report_theme) = report_theme {
+        profiler.set_report_theme(report_theme);
+    }
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(profiler));
+
+    let inner_clock: Arc<ManualClock> = clock.clone();
+    let parse = profiler.keyed_function_wrapper("parse", move |n: u64| {
+        inner_clock.advance(Duration::from_nanos(n));
+    });
+    let load = profiler.keyed_function_wrapper("load", move |n: u64| {
+        for _ in 0..1234 {
+            parse(n);
+        }
+        clock.advance(Duration::from_micros(766));
+    });
+    load(1000);
+
+    profiler.get_report_string()
+}
+
+#[test]
+fn default_theme_matches_the_original_report() {
+    let expected: &str = concat!(
+        "\n█ PROFILE: load █\n",
+        "=====================\n",
+        "Name: parse\n",
+        "Time: [1.23ms] — T%: 61.70%\n",
+        "NCalls: [1234] — PerCall: [1.00μs]\n",
+        "——\n",
+        "Profile Time: [2.00ms]\n",
+        "NCalls: [1] — PerCall: [2.00ms]\n",
+        "——————\n",
+        "\n",
+        "――― Total Time: [2.00ms] ―――\n",
+        "\n\n\n",
+    );
+    assert_eq!(themed_report(None), expected);
+    assert_eq!(themed_report(Some(ReportTheme::unicode())), expected);
+}
+
+#[test]
+fn ascii_theme_report() {
+    let expected: &str = concat!(
+        "\n# PROFILE: load #\n",
+        "=================\n",
+        "Name: parse\n",
+        "Time: [1.23ms] - T%: 61.70%\n",
+        "NCalls: [1,234] - PerCall: [1.00us]\n",
+        "--\n",
+        "Profile Time: [2.00ms]\n",
+        "NCalls: [1] - PerCall: [2.00ms]\n",
+        "------\n",
+        "\n",
+        "--- Total Time: [2.00ms] ---\n",
+        "\n\n\n",
+    );
+    assert_eq!(themed_report(Some(ReportTheme::ascii())), expected);
+}
+
+#[test]
+fn compact_theme_report() {
+    let expected: &str = concat!(
+        "\nPROFILE: load\n",
+        "Name: parse | Time: [1.23ms] | T%: 61.70% | NCalls: [1234] | PerCall: [1.00μs]\n",
+        "Profile Time: [2.00ms] | NCalls: [1] | PerCall: [2.00ms]\n",
+        "Total Time: [2.00ms]\n",
+    );
+    assert_eq!(themed_report(Some(ReportTheme::compact())), expected);
+}
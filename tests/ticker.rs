@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use timer_module::clock::{Clock, ManualClock};
+use timer_module::ticker::{MissedTickBehavior, Tick, TickStatistics, Ticker};
+
+const PERIOD: Duration = Duration::from_millis(10);
+
+fn manual_ticker(behavior: MissedTickBehavior) -> (Ticker, Arc<ManualClock>) {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut ticker: Ticker = Ticker::new(PERIOD);
+    ticker
+        .set_clock(clock.clone())
+        .set_missed_tick_behavior(behavior);
+    (ticker, clock)
+}
+
+fn millis(tick: &Tick) -> (u128, u128, usize) {
+    (
+        tick.scheduled.as_millis(),
+        tick.actual.as_millis(),
+        tick.missed,
+    )
+}
+
+#[test]
+fn ticks_wait_for_their_period() {
+    let (mut ticker, clock) = manual_ticker(MissedTickBehavior::Burst);
+    let ticks: Vec<(u128, u128, usize)> = ticker.by_ref().take(3).map(|t| millis(&t)).collect();
+    assert_eq!(ticks, vec![(0, 0, 0), (10, 10, 0), (20, 20, 0)]);
+
+    // Without drift correction the next tick is a period after the late one.
+    ticker.set_drift_correction(false);
+    clock.advance(Duration::from_millis(14));
+    assert_eq!(millis(&ticker.tick()), (30, 34, 0));
+    assert_eq!(millis(&ticker.tick()), (44, 44, 0));
+}
+
+#[test]
+fn missed_ticks_follow_the_configured_behavior() {
+    let expected = [
+        (
+            MissedTickBehavior::Burst,
+            [(10, 35, 0), (20, 35, 0), (30, 35, 0)],
+        ),
+        (
+            MissedTickBehavior::Delay,
+            [(10, 35, 2), (45, 45, 0), (55, 55, 0)],
+        ),
+        (
+            MissedTickBehavior::Skip,
+            [(10, 35, 2), (40, 40, 0), (50, 50, 0)],
+        ),
+    ];
+    for (behavior, ticks) in expected {
+        let (mut ticker, clock) = manual_ticker(behavior);
+        ticker.tick();
+        clock.advance(Duration::from_millis(35));
+        let actual: Vec<(u128, u128, usize)> =
+            ticker.by_ref().take(3).map(|t| millis(&t)).collect();
+        assert_eq!(actual, ticks, "{:?}", behavior);
+    }
+}
+
+#[test]
+fn skip_survives_long_stalls_with_short_periods() {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut ticker: Ticker = Ticker::new(Duration::from_nanos(1));
+    ticker
+        .set_clock(clock.clone())
+        .set_missed_tick_behavior(MissedTickBehavior::Skip);
+    ticker.tick();
+
+    // More missed ticks than fit in a u32.
+    clock.advance(Duration::from_nanos(1 << 33));
+    let late: Tick = ticker.tick();
+    assert_eq!(late.missed, (1 << 33) - 1);
+    let next: Tick = ticker.tick();
+    assert_eq!(next.scheduled, late.actual + Duration::from_nanos(1));
+}
+
+#[test]
+fn statistics_track_jitter_and_missed_ticks() {
+    let (mut ticker, clock) = manual_ticker(MissedTickBehavior::Delay);
+    ticker.tick();
+    clock.advance(Duration::from_millis(16));
+    ticker.tick();
+    clock.advance(Duration::from_millis(30));
+    ticker.tick();
+
+    // Jitter: 0ms, 6ms and 26ms late, the last missing 2 ticks.
+    let statistics: &TickStatistics = ticker.get_statistics();
+    assert_eq!((statistics.ticks, statistics.missed_ticks), (3, 2));
+    assert_eq!(statistics.min_jitter_ns, 0.0);
+    assert_eq!(statistics.max_jitter_ns, 26e6);
+    assert!((statistics.mean_jitter_ns - 32e6 / 3.0).abs() < 1.0);
+    assert!((statistics.get_jitter_stddev_ns() - 13_613_718.6).abs() < 1.0);
+
+    ticker.reset();
+    assert_eq!(ticker.get_statistics().ticks, 0);
+    assert_eq!(millis(&ticker.tick()), (0, 0, 0));
+}
+
+#[test]
+fn paused_time_does_not_count_towards_ticks() {
+    let (mut ticker, clock) = manual_ticker(MissedTickBehavior::Delay);
+    ticker.tick();
+    clock.advance(Duration::from_millis(4));
+    ticker.pause();
+    clock.advance(Duration::from_millis(50));
+    ticker.start();
+
+    // 4ms elapsed before the pause, so the next tick sleeps 6ms more.
+    assert_eq!(millis(&ticker.tick()), (10, 10, 0));
+    assert_eq!(clock.now(), Duration::from_millis(60));
+    assert_eq!(ticker.get_statistics().missed_ticks, 0);
+}
+
+// Wakes from every sleep a millisecond before it is due.
+struct EarlyClock(ManualClock);
+
+impl Clock for EarlyClock {
+    fn now(&self) -> Duration {
+        self.0.now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.0
+            .advance(duration.saturating_sub(Duration::from_millis(1)));
+    }
+}
+
+#[test]
+fn ticks_tolerate_clocks_waking_early() {
+    let mut ticker: Ticker = Ticker::new(PERIOD);
+    ticker.set_clock(Arc::new(EarlyClock(ManualClock::new())));
+    let ticks: Vec<(u128, u128, usize)> = ticker.by_ref().take(3).map(|t| millis(&t)).collect();
+    assert_eq!(ticks, vec![(0, 0, 0), (10, 9, 0), (20, 19, 0)]);
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use timer_module::clock::ManualClock;
+use timer_module::timer::{TimerModule, TimerSnapshot};
+
+fn lapped_timer() -> TimerModule {
+    let mut timer_module: TimerModule = TimerModule::new();
+    timer_module.start();
+    thread::sleep(Duration::from_millis(2));
+    timer_module.lap();
+    thread::sleep(Duration::from_millis(2));
+    timer_module.lap();
+    timer_module
+}
+
+#[test]
+fn paused_snapshot_restores_duration_and_laps() {
+    let mut timer_module: TimerModule = lapped_timer();
+    timer_module.pause();
+    let snapshot: TimerSnapshot = timer_module.snapshot();
+    assert!(!snapshot.is_running);
+    assert_eq!(snapshot.laps.len(), 2);
+    assert!(snapshot.laps.iter().sum::<Duration>() <= snapshot.duration);
+
+    let mut restored: TimerModule = TimerModule::from_snapshot(&snapshot);
+    assert!(!restored.is_running());
+    assert_eq!(restored.get_duration(), snapshot.duration);
+    assert_eq!(restored.get_laps(), snapshot.laps.as_slice());
+    assert_eq!(restored.snapshot().duration, snapshot.duration);
+}
+
+#[test]
+fn running_snapshot_resumes_with_the_time_since_it_was_taken() {
+    let mut timer_module: TimerModule = lapped_timer();
+    let mut snapshot: TimerSnapshot = timer_module.snapshot();
+    assert!(snapshot.is_running);
+    snapshot.timestamp -= Duration::from_secs(60);
+
+    let mut restored: TimerModule = TimerModule::from_snapshot(&snapshot);
+    assert!(restored.is_running());
+    assert!(restored.get_duration() >= snapshot.duration + Duration::from_secs(60));
+    assert_eq!(restored.get_laps(), snapshot.laps.as_slice());
+
+    // Laps keep counting from the restored laps.
+    let lap: Duration = restored.lap();
+    assert!(lap >= Duration::from_secs(60));
+    assert_eq!(restored.get_laps().len(), 3);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn snapshot_round_trips_through_serde() {
+    let mut timer_module: TimerModule = lapped_timer();
+    let snapshot: TimerSnapshot = timer_module.snapshot();
+
+    let json: String = serde_json::to_string(&snapshot).unwrap();
+    let parsed: TimerSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, snapshot);
+}
+
+#[test]
+fn timer_runs_on_its_clock() {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut timer_module: TimerModule = TimerModule::new();
+    timer_module.set_clock(clock.clone()).start();
+    clock.advance(Duration::from_millis(30));
+    assert_eq!(timer_module.lap(), Duration::from_millis(30));
+
+    timer_module.pause();
+    clock.advance(Duration::from_millis(100));
+    timer_module.start();
+    clock.advance(Duration::from_millis(20));
+    assert_eq!(timer_module.get_duration(), Duration::from_millis(50));
+    assert_eq!(timer_module.lap(), Duration::from_millis(20));
+
+    timer_module.set_time(2);
+    clock.advance(Duration::from_millis(5));
+    assert_eq!(timer_module.get_duration(), Duration::from_millis(2005));
+}
@@ -0,0 +1,162 @@
+#![cfg(all(feature = "tracing", not(feature = "disable-profiling")))]
+
+use std::fmt::Debug;
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tracing::field::{Field, Visit};
+use tracing::{info_span, Dispatch, Event, Span, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+use timer_module::clock::ManualClock;
+use timer_module::layer::ProfilerLayer;
+use timer_module::metrics::CallableMetrics;
+use timer_module::profiler::TimeProfiler;
+use timer_module::timer::{LoggedTimer, TimerModule};
+
+struct EventCollector(Arc<Mutex<Vec<String>>>);
+
+impl<S: Subscriber> Layer<S> for EventCollector {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut fields: FieldCollector = FieldCollector(Vec::new());
+        event.record(&mut fields);
+        self.0.lock().unwrap().push(fields.0.join(" "));
+    }
+}
+
+struct FieldCollector(Vec<String>);
+
+impl Visit for FieldCollector {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+}
+
+#[test]
+fn layer_records_spans_with_their_nesting() {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+    let profiler: Arc<TimeProfiler> = Arc::new(profiler);
+
+    let subscriber = tracing_subscriber::registry().with(ProfilerLayer::new(profiler.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        let outer = info_span!("outer");
+        for _ in 0..3 {
+            let _outer = outer.enter();
+            clock.advance(Duration::from_nanos(5));
+            let inner = info_span!("inner");
+            inner.in_scope(|| clock.advance(Duration::from_nanos(10)));
+        }
+        // Time outside of the span is not part of it.
+        clock.advance(Duration::from_nanos(100));
+        let _unentered = info_span!("unentered");
+    });
+
+    let outer: CallableMetrics = profiler.find_callable("outer").unwrap();
+    assert_eq!((outer.ncalls, outer.time_ns), (1, 45.0));
+    let inner: CallableMetrics = profiler.find_callable("inner").unwrap();
+    assert_eq!((inner.ncalls, inner.time_ns), (3, 30.0));
+    assert!(profiler.find_callable("unentered").is_none());
+
+    let children: Vec<String> = profiler
+        .get_children("outer")
+        .into_iter()
+        .map(|child| child.name)
+        .collect();
+    assert_eq!(children, vec!["inner"]);
+}
+
+#[test]
+fn spans_beneath_an_open_root_are_reported() {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+    let profiler: Arc<TimeProfiler> = Arc::new(profiler);
+
+    let subscriber = tracing_subscriber::registry().with(ProfilerLayer::new(profiler.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        let server = info_span!("server");
+        let _server = server.enter();
+        for _ in 0..3 {
+            info_span!("request").in_scope(|| clock.advance(Duration::from_nanos(10)));
+        }
+
+        let children: Vec<(String, usize)> = profiler
+            .get_children("server")
+            .into_iter()
+            .map(|child| (child.name, child.ncalls))
+            .collect();
+        assert_eq!(children, vec![(String::from("request"), 3)]);
+        assert!(!profiler.get_timing_metrics().is_empty());
+        assert!(profiler.get_report_string().contains("request"));
+    });
+}
+
+#[test]
+fn layer_times_concurrent_enters_on_each_thread() {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+    let profiler: Arc<TimeProfiler> = Arc::new(profiler);
+
+    let subscriber = tracing_subscriber::registry().with(ProfilerLayer::new(profiler.clone()));
+    let dispatch: Dispatch = Dispatch::new(subscriber);
+    let span: Span = tracing::dispatcher::with_default(&dispatch, || info_span!("shared"));
+    let barrier: Barrier = Barrier::new(3);
+    thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| {
+                let _entered = span.enter();
+                barrier.wait();
+                barrier.wait();
+            });
+        }
+        barrier.wait();
+        clock.advance(Duration::from_nanos(10));
+        barrier.wait();
+    });
+    drop(span);
+
+    let shared: CallableMetrics = profiler.find_callable("shared").unwrap();
+    assert_eq!((shared.ncalls, shared.time_ns), (1, 20.0));
+}
+
+#[test]
+fn profiler_and_timer_emit_events() {
+    let clock: Arc<ManualClock> = Arc::new(ManualClock::new());
+    let mut profiler: TimeProfiler = TimeProfiler::new(false);
+    profiler.set_clock(clock.clone());
+    let profiler: &'static TimeProfiler = Box::leak(Box::new(profiler));
+
+    let inner_clock: Arc<ManualClock> = clock.clone();
+    let inner = profiler.keyed_function_wrapper("inner", move |n: u64| {
+        inner_clock.advance(Duration::from_nanos(n));
+    });
+    let outer = profiler.keyed_function_wrapper("outer", move |n: u64| {
+        inner(n);
+        inner(n);
+        clock.advance(Duration::from_nanos(5));
+    });
+    outer(10);
+
+    let events: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let subscriber = tracing_subscriber::registry().with(EventCollector(events.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        profiler.emit_report();
+        let timer_module: LoggedTimer = TimerModule::new().set_log_on_drop("load");
+        drop(timer_module);
+        // Without a label nothing is logged on drop.
+        drop(TimerModule::new());
+    });
+
+    let events: Vec<String> = events.lock().unwrap().clone();
+    assert_eq!(events.len(), 4);
+    assert!(events[0].contains("name=inner"));
+    assert!(events[1].starts_with("message=profiled outer name=outer"));
+    assert!(events[1].ends_with("ncalls=1 total_ns=25.0 percall_ns=25.0"));
+    assert!(events[2].contains("parent=outer ncalls=2 total_ns=20.0 percall_ns=10.0"));
+    assert!(events[3].starts_with("message=timed block load took"));
+    assert!(events[3].ends_with("label=load elapsed_ns=0"));
+}
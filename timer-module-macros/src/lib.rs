@@ -0,0 +1,116 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Expr, ItemFn, LitStr, ReturnType, Type};
+
+/// Records every call of the annotated function with a `TimeProfiler`.
+///
+/// ```ignore
+/// #[profile]
+/// fn parse_config(path: &str) -> Config { ... }
+///
+/// #[profile(name = "load", profiler = PROFILER.deref())]
+/// fn load_config(path: &str) -> io::Result<Config> { ... }
+/// ```
+///
+/// Calls are recorded with `TimeProfiler::global()` unless a `profiler`
+/// expression evaluating to a `&TimeProfiler` is given, and are named after
+/// the function's path below its module, such as `Config::new` for a method,
+/// unless a `name` is given. Functions returning a `Result` have their `Ok`
+/// and `Err` outcomes recorded separately.
+#[proc_macro_attribute]
+pub fn profile(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name: Option<LitStr> = None;
+    let mut profiler: Option<Expr> = None;
+    let args_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+            return Ok(());
+        }
+        if meta.path.is_ident("profiler") {
+            profiler = Some(meta.value()?.parse()?);
+            return Ok(());
+        }
+        Err(meta.error("expected `name` or `profiler`"))
+    });
+    parse_macro_input!(args with args_parser);
+
+    let function = parse_macro_input!(item as ItemFn);
+    match expand_profile(function, name, profiler) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand_profile(
+    function: ItemFn,
+    name: Option<LitStr>,
+    profiler: Option<Expr>,
+) -> syn::Result<TokenStream2> {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = function;
+    if let Some(asyncness) = sig.asyncness {
+        return Err(syn::Error::new_spanned(
+            asyncness,
+            "#[profile] does not support async functions",
+        ));
+    }
+    if let Some(constness) = sig.constness {
+        return Err(syn::Error::new_spanned(
+            constness,
+            "#[profile] does not support const functions",
+        ));
+    }
+
+    // The attribute cannot see an enclosing `impl` block, so the default name
+    // is taken from the type name of a marker function nested in the body.
+    let call_site: TokenStream2 = match name {
+        Some(name) => quote! {
+            ::timer_module::profiler::CallSite::new(#name, ::core::module_path!())
+        },
+        None => quote! {
+            ::timer_module::profiler::CallSite::from_path(::core::module_path!(), || {
+                ::core::any::type_name_of_val(&__timer_module_marker)
+            })
+        },
+    };
+    let profiler: TokenStream2 = match profiler {
+        Some(profiler) => quote!(#profiler),
+        None => quote!(::timer_module::profiler::TimeProfiler::global()),
+    };
+    let mut profile_call: TokenStream2 = quote!(profile_call);
+    if returns_result(&sig.output) {
+        profile_call = quote!(profile_result_call);
+    }
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            fn __timer_module_marker() {}
+            static __TIMER_MODULE_CALL_SITE: ::timer_module::profiler::CallSite = #call_site;
+            let __timer_module_profiler: &::timer_module::profiler::TimeProfiler = #profiler;
+            __timer_module_profiler.#profile_call(&__TIMER_MODULE_CALL_SITE, move || #block)
+        }
+    })
+}
+
+// Only the spelling of the return type is visible to the macro, so any type
+// named `Result` (std, io, anyhow, ...) is treated as a result.
+fn returns_result(output: &ReturnType) -> bool {
+    let return_type: &Type = match output {
+        ReturnType::Type(_, return_type) => return_type,
+        ReturnType::Default => return false,
+    };
+    match return_type {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Result"),
+        _ => false,
+    }
+}